@@ -0,0 +1,633 @@
+/*
+MIT License
+
+Copyright (c) 2024 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Absences, such as public holidays, vacation, and sick days.
+//!
+//! Absences are taken from the `absences` table of the config file, either
+//! listed directly or imported from local iCalendar (`.ics`) files. Days of
+//! absence have no target working time, are not reported as missing, and are
+//! flagged if time was logged on them.
+
+use crate::calendar::TimeZone;
+use crate::print_warning;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+/// The kind of an absence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AbsenceKind {
+    Holiday,
+    Vacation,
+    Sick,
+}
+
+impl Display for AbsenceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Holiday => "Holiday",
+            Self::Vacation => "Vacation",
+            Self::Sick => "Sick day",
+        })
+    }
+}
+
+/// An iCalendar file of the config file. Each event of the file is an
+/// absence of the given kind.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IcsConfig {
+    path: PathBuf,
+    kind: AbsenceKind,
+}
+
+/// The `absences` table of the config file, such as:
+///
+/// ```toml
+/// [absences]
+/// holidays = ["2024-12-25", "2024-12-26"]
+/// vacation = ["2024-08-01..2024-08-14"]
+/// ics = [{ path = "~/holidays.ics", kind = "holiday" }]
+/// ```
+///
+/// Entries are single days or inclusive ranges of days.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AbsencesConfig {
+    holidays: Option<Vec<String>>,
+    vacation: Option<Vec<String>>,
+    sick: Option<Vec<String>>,
+    ics: Option<Vec<IcsConfig>>,
+}
+
+impl AbsencesConfig {
+    /// Returns the values of `self`, with the missing keys taken from `base`.
+    pub fn or(self, base: &Self) -> Self {
+        Self {
+            holidays: self.holidays.or_else(|| base.holidays.clone()),
+            vacation: self.vacation.or_else(|| base.vacation.clone()),
+            sick: self.sick.or_else(|| base.sick.clone()),
+            ics: self.ics.or_else(|| base.ics.clone()),
+        }
+    }
+}
+
+/// A day of absence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Absence {
+    pub kind: AbsenceKind,
+    /// Name of the holiday or summary of the calendar event, if any.
+    pub name: Option<String>,
+}
+
+impl Display for Absence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{}: {name}", self.kind),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+/// All days of absence.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Absences {
+    days: BTreeMap<NaiveDate, Absence>,
+}
+
+impl Absences {
+    /// Creates the absences from the settings of the config file and reads
+    /// the referenced iCalendar files.
+    ///
+    /// Times of calendar events are converted to `timezone`.
+    pub fn from_config(config: &AbsencesConfig, timezone: TimeZone) -> Result<Self, String> {
+        let mut absences = Self::default();
+        let lists = [
+            (AbsenceKind::Holiday, &config.holidays),
+            (AbsenceKind::Vacation, &config.vacation),
+            (AbsenceKind::Sick, &config.sick),
+        ];
+        for (kind, entries) in lists {
+            for entry in entries.iter().flatten() {
+                let (first, last) = parse_days(entry)?;
+                absences.insert(first, last, kind, None);
+            }
+        }
+
+        for ics in config.ics.iter().flatten() {
+            let path = expand_home(&ics.path);
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| format!("failed to read {}: {e}", path.display()))?;
+            let events = parse_ics(&content, timezone)
+                .map_err(|e| format!("invalid {}: {e}", path.display()))?;
+            let recurring = events
+                .iter()
+                .filter(|event| event.recurring)
+                .map(|event| format!("`{}`", event.summary.as_deref().unwrap_or("<No Summary>")))
+                .collect::<Vec<_>>();
+            if !recurring.is_empty() && first_warning_for(&path) {
+                print_warning(
+                    &format!(
+                        "Recurring events in {} are only counted once, as recurrence rules (`RRULE`) are not supported: {}",
+                        path.display(),
+                        recurring.join(", ")
+                    ),
+                    0,
+                );
+            }
+            for event in events {
+                absences.insert(event.first, event.last, ics.kind, event.summary);
+            }
+        }
+        Ok(absences)
+    }
+
+    /// Marks all days from `first` to `last` (inclusive) as absent. Existing
+    /// absences are kept.
+    fn insert(
+        &mut self,
+        first: NaiveDate,
+        last: NaiveDate,
+        kind: AbsenceKind,
+        name: Option<String>,
+    ) {
+        for day in first.iter_days().take_while(|day| *day <= last) {
+            self.days.entry(day).or_insert_with(|| Absence {
+                kind,
+                name: name.clone(),
+            });
+        }
+    }
+
+    /// Returns the absence of the given day, if any.
+    pub fn get(&self, day: NaiveDate) -> Option<&Absence> {
+        self.days.get(&day)
+    }
+}
+
+/// Parses a single day, such as `2024-12-25`, or an inclusive range of days,
+/// such as `2024-08-01..2024-08-14`.
+fn parse_days(entry: &str) -> Result<(NaiveDate, NaiveDate), String> {
+    let parse = |date: &str| {
+        date.trim()
+            .parse::<NaiveDate>()
+            .map_err(|e| format!("invalid absence `{entry}`: {e}"))
+    };
+    let (first, last) = match entry.split_once("..") {
+        Some((first, last)) => (parse(first)?, parse(last)?),
+        None => (parse(entry)?, parse(entry)?),
+    };
+    if first > last {
+        return Err(format!(
+            "invalid absence `{entry}`: the first day is after the last day"
+        ));
+    }
+    Ok((first, last))
+}
+
+/// Returns `true` the first time it is called for `path`. The absences are
+/// read once per profile and user, but warnings about a file are only
+/// printed once.
+fn first_warning_for(path: &Path) -> bool {
+    static WARNED: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+    WARNED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(path.to_path_buf())
+}
+
+/// Expands a leading `~` to the home directory.
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// An event of an iCalendar file.
+#[derive(Debug, PartialEq, Eq)]
+struct IcsDays {
+    /// First day of the event.
+    first: NaiveDate,
+    /// Last day of the event (inclusive).
+    last: NaiveDate,
+    summary: Option<String>,
+    /// Whether the event has a recurrence rule, which is not evaluated.
+    recurring: bool,
+}
+
+/// Parses the events of an iCalendar file into their days and summary.
+///
+/// Only `DTSTART`, `DTEND`, `DURATION`, and `SUMMARY` are evaluated. Times in
+/// UTC or with a `TZID` are converted to `timezone`; floating times are taken
+/// as they are. Recurring events (`RRULE`) are taken as a single occurrence
+/// and marked as recurring.
+fn parse_ics(content: &str, timezone: TimeZone) -> Result<Vec<IcsDays>, String> {
+    // Long lines are folded by a line break followed by a space or tab.
+    let unfolded = content
+        .replace("\r\n", "\n")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+
+    let mut events = Vec::new();
+    let mut event: Option<IcsEvent> = None;
+    // The nested components, such as `VCALENDAR`, `VEVENT`, and `VALARM`.
+    let mut components = Vec::new();
+    for line in unfolded.lines() {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        // Parameters such as `;VALUE=DATE` are separated by `;`.
+        let (name, params) = name.split_once(';').unwrap_or((name, ""));
+        match name {
+            "BEGIN" => {
+                components.push(value);
+                if value == "VEVENT" {
+                    event = Some(IcsEvent::default());
+                }
+                continue;
+            }
+            "END" => {
+                if components.pop() != Some(value) {
+                    return Err(format!("unexpected `END:{value}`"));
+                }
+                if value == "VEVENT" {
+                    if let Some(ics_event) = event.take() {
+                        events.push(ics_event.into_days()?);
+                    }
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        // Properties of nested components, such as the summary of an alarm,
+        // don't belong to the event.
+        if components.last() != Some(&"VEVENT") {
+            continue;
+        }
+        match (name, event.as_mut()) {
+            ("DTSTART", Some(ics_event)) => {
+                ics_event.start = Some(parse_ics_time(value, params, timezone)?);
+            }
+            ("DTEND", Some(ics_event)) => {
+                ics_event.end = Some(parse_ics_time(value, params, timezone)?);
+            }
+            ("DURATION", Some(ics_event)) => {
+                ics_event.duration = Some(parse_ics_duration(value)?);
+            }
+            ("RRULE", Some(ics_event)) => ics_event.recurring = true,
+            ("SUMMARY", Some(ics_event)) => {
+                ics_event.summary = Some(value.replace("\\,", ",").replace("\\;", ";"));
+            }
+            _ => {}
+        }
+    }
+    Ok(events)
+}
+
+/// A `DTSTART` or `DTEND` value of an iCalendar file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IcsTime {
+    /// A whole day.
+    Date(NaiveDate),
+    /// A point in time in the configured time zone.
+    DateTime(NaiveDateTime),
+}
+
+impl IcsTime {
+    /// Returns the time after the given duration. Whole days keep a date a
+    /// date.
+    fn checked_add(self, duration: TimeDelta) -> Option<Self> {
+        match self {
+            Self::Date(date) if duration.num_seconds() % (24 * 60 * 60) == 0 => date
+                .checked_add_signed(TimeDelta::days(duration.num_days()))
+                .map(Self::Date),
+            Self::Date(date) => date
+                .and_time(NaiveTime::MIN)
+                .checked_add_signed(duration)
+                .map(Self::DateTime),
+            Self::DateTime(datetime) => datetime.checked_add_signed(duration).map(Self::DateTime),
+        }
+    }
+
+    const fn first_day(self) -> NaiveDate {
+        match self {
+            Self::Date(date) => date,
+            Self::DateTime(datetime) => datetime.date(),
+        }
+    }
+
+    /// Returns the last day before this time as the exclusive end of an
+    /// event. An event ending at midnight doesn't cover the day of its end.
+    fn last_day_before(self) -> NaiveDate {
+        match self {
+            Self::Date(date) => date.pred_opt().unwrap_or(date),
+            Self::DateTime(datetime) => (datetime - TimeDelta::seconds(1)).date(),
+        }
+    }
+}
+
+/// The relevant properties of a `VEVENT` of an iCalendar file.
+#[derive(Debug, Default)]
+struct IcsEvent {
+    start: Option<IcsTime>,
+    /// The exclusive end.
+    end: Option<IcsTime>,
+    /// The duration, as alternative to the end.
+    duration: Option<TimeDelta>,
+    summary: Option<String>,
+    recurring: bool,
+}
+
+impl IcsEvent {
+    /// Returns the days and the summary of the event.
+    fn into_days(self) -> Result<IcsDays, String> {
+        let start = self.start.ok_or("event without DTSTART")?;
+        let end = match (self.end, self.duration) {
+            (Some(end), _) => Some(end),
+            (None, Some(duration)) => Some(
+                start
+                    .checked_add(duration)
+                    .ok_or("event with an invalid DURATION")?,
+            ),
+            (None, None) => None,
+        };
+        let first = start.first_day();
+        let last = end.map_or(first, |end| end.last_day_before().max(first));
+        Ok(IcsDays {
+            first,
+            last,
+            summary: self.summary,
+            recurring: self.recurring,
+        })
+    }
+}
+
+/// Parses an iCalendar date or date-time value, such as `20241225`,
+/// `20241225T090000Z`, or `20241225T090000` with a `TZID` parameter.
+///
+/// Date-times in UTC or with a `TZID` are converted to `timezone`.
+fn parse_ics_time(value: &str, params: &str, timezone: TimeZone) -> Result<IcsTime, String> {
+    let invalid = |e: chrono::ParseError| format!("invalid date `{value}`: {e}");
+    if !value.contains('T') {
+        return NaiveDate::parse_from_str(value, "%Y%m%d")
+            .map(IcsTime::Date)
+            .map_err(invalid);
+    }
+
+    let (local, is_utc) = value
+        .strip_suffix('Z')
+        .map_or((value, false), |local| (local, true));
+    let datetime = NaiveDateTime::parse_from_str(local, "%Y%m%dT%H%M%S").map_err(invalid)?;
+    let tzid = params
+        .split(';')
+        .find_map(|param| param.strip_prefix("TZID="))
+        .map(|tzid| tzid.trim_matches('"'));
+    let datetime = match (is_utc, tzid) {
+        (true, _) => timezone.naive_of(&datetime.and_utc()),
+        (false, Some(tzid)) => {
+            let tz = tzid
+                .parse::<chrono_tz::Tz>()
+                .map_err(|_| format!("unsupported time zone `{tzid}` of `{value}`"))?;
+            let zoned = datetime
+                .and_local_timezone(tz)
+                .earliest()
+                .ok_or_else(|| format!("`{value}` doesn't exist in `{tzid}`"))?;
+            timezone.naive_of(&zoned)
+        }
+        // Floating time, which is the same in every time zone.
+        (false, None) => datetime,
+    };
+    Ok(IcsTime::DateTime(datetime))
+}
+
+/// Parses an iCalendar duration, such as `P2D`, `PT8H30M`, or `P1W`.
+fn parse_ics_duration(value: &str) -> Result<TimeDelta, String> {
+    let invalid = || format!("invalid duration `{value}`");
+    let rest = value.strip_prefix('+').unwrap_or(value);
+    let rest = rest.strip_prefix('P').ok_or_else(invalid)?;
+
+    let mut secs = 0_i64;
+    let mut number = String::new();
+    let mut is_time = false;
+    for c in rest.chars() {
+        let unit_secs = match c {
+            '0'..='9' => {
+                number.push(c);
+                continue;
+            }
+            'T' if number.is_empty() && !is_time => {
+                is_time = true;
+                continue;
+            }
+            'W' if !is_time => 7 * 24 * 60 * 60,
+            'D' if !is_time => 24 * 60 * 60,
+            'H' if is_time => 60 * 60,
+            'M' if is_time => 60,
+            'S' if is_time => 1,
+            _ => return Err(invalid()),
+        };
+        let value = number.parse::<i64>().map_err(|_| invalid())?;
+        secs = value
+            .checked_mul(unit_secs)
+            .and_then(|value_secs| secs.checked_add(value_secs))
+            .ok_or_else(invalid)?;
+        number.clear();
+    }
+    if !number.is_empty() || rest.is_empty() {
+        return Err(invalid());
+    }
+    TimeDelta::try_seconds(secs).ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_ics_reads_all_day_and_timed_events() {
+        let berlin = "Europe/Berlin".parse::<TimeZone>().unwrap();
+        let ics = "BEGIN:VCALENDAR\r\n\
+VERSION:2.0\r\n\
+BEGIN:VEVENT\r\n\
+DTSTART;VALUE=DATE:20241225\r\n\
+DTEND;VALUE=DATE:20241227\r\n\
+SUMMARY:Christmas\\, both days\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+DTSTART:20240805T080000Z\r\n\
+DTEND:20240806T160000Z\r\n\
+SUMMARY:Vacation at the\r\n \x20sea\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+DTSTART;VALUE=DATE:20240101\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+DTSTART:20240902T080000\r\n\
+DTEND:20240904T000000\r\n\
+RRULE:FREQ=YEARLY\r\n\
+SUMMARY:Conference\r\n\
+END:VEVENT\r\n\
+END:VCALENDAR\r\n";
+
+        let days = |first, last, summary: Option<&str>, recurring| IcsDays {
+            first: date(first),
+            last: date(last),
+            summary: summary.map(str::to_string),
+            recurring,
+        };
+        assert_eq!(
+            parse_ics(ics, berlin).unwrap(),
+            [
+                days(
+                    "2024-12-25",
+                    "2024-12-26",
+                    Some("Christmas, both days"),
+                    false
+                ),
+                days(
+                    "2024-08-05",
+                    "2024-08-06",
+                    Some("Vacation at the sea"),
+                    false
+                ),
+                days("2024-01-01", "2024-01-01", None, false),
+                // Ends at midnight, which excludes the 4th.
+                days("2024-09-02", "2024-09-03", Some("Conference"), true),
+            ]
+        );
+
+        let with_alarm = "BEGIN:VCALENDAR\n\
+BEGIN:VEVENT\n\
+DTSTART;VALUE=DATE:20241231\n\
+SUMMARY:New Year's Eve\n\
+BEGIN:VALARM\n\
+ACTION:DISPLAY\n\
+SUMMARY:Reminder\n\
+END:VALARM\n\
+END:VEVENT\n\
+END:VCALENDAR\n";
+        assert_eq!(
+            parse_ics(with_alarm, berlin).unwrap(),
+            [days(
+                "2024-12-31",
+                "2024-12-31",
+                Some("New Year's Eve"),
+                false
+            )]
+        );
+        assert!(parse_ics("BEGIN:VEVENT\nEND:VCALENDAR", berlin).is_err());
+
+        let zoned = "BEGIN:VEVENT\n\
+DTSTART:20240909T230000Z\n\
+DTEND:20240910T220000Z\n\
+END:VEVENT\n\
+BEGIN:VEVENT\n\
+DTSTART;TZID=America/New_York:20240916T200000\n\
+DURATION:PT2H\n\
+END:VEVENT\n\
+BEGIN:VEVENT\n\
+DTSTART;VALUE=DATE:20241001\n\
+DURATION:P3D\n\
+END:VEVENT\n";
+        assert_eq!(
+            parse_ics(zoned, berlin).unwrap(),
+            [
+                // From 01:00 to midnight in Berlin.
+                days("2024-09-10", "2024-09-10", None, false),
+                // From 02:00 to 04:00 in Berlin.
+                days("2024-09-17", "2024-09-17", None, false),
+                days("2024-10-01", "2024-10-03", None, false),
+            ]
+        );
+        let unknown_tzid = "BEGIN:VEVENT\nDTSTART;TZID=Nowhere:20240916T200000\nEND:VEVENT\n";
+        assert!(parse_ics(unknown_tzid, berlin).is_err());
+        assert!(parse_ics("BEGIN:VEVENT\nDTSTART:2024\nEND:VEVENT", berlin).is_err());
+    }
+
+    #[test]
+    fn parse_ics_duration_supports_weeks_days_and_times() {
+        assert_eq!(parse_ics_duration("P1W"), Ok(TimeDelta::weeks(1)));
+        assert_eq!(
+            parse_ics_duration("P1DT8H30M"),
+            Ok(TimeDelta::days(1) + TimeDelta::minutes(8 * 60 + 30))
+        );
+        assert_eq!(parse_ics_duration("+PT15S"), Ok(TimeDelta::seconds(15)));
+        for invalid in ["", "P", "P1H", "PT1D", "-P1D", "P1", "P99999999999999999W"] {
+            assert!(parse_ics_duration(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn first_warning_for_is_true_once_per_path() {
+        let path = Path::new("/nonexistent/first_warning_for.ics");
+        assert!(first_warning_for(path));
+        assert!(!first_warning_for(path));
+        assert!(first_warning_for(&path.with_extension("ical")));
+    }
+
+    #[test]
+    fn absences_from_config() {
+        let config = toml::from_str::<AbsencesConfig>(
+            r#"
+            holidays = ["2024-12-25"]
+            vacation = ["2024-08-01..2024-08-03", "2024-12-25"]
+            "#,
+        )
+        .unwrap();
+        let absences = Absences::from_config(&config, TimeZone::Local).unwrap();
+
+        assert_eq!(
+            absences.get(date("2024-12-25")).map(|a| a.kind),
+            Some(AbsenceKind::Holiday)
+        );
+        assert_eq!(
+            absences.get(date("2024-08-02")).unwrap().to_string(),
+            "Vacation"
+        );
+        assert!(absences.get(date("2024-08-04")).is_none());
+
+        for invalid in [
+            "sick = [\"2024-13-01\"]",
+            "sick = [\"2024-08-02..2024-08-01\"]",
+        ] {
+            let config = toml::from_str::<AbsencesConfig>(invalid).unwrap();
+            assert!(Absences::from_config(&config, TimeZone::Local).is_err());
+        }
+        let config = toml::from_str::<AbsencesConfig>(
+            "ics = [{ path = \"/nonexistent.ics\", kind = \"holiday\" }]",
+        )
+        .unwrap();
+        assert!(Absences::from_config(&config, TimeZone::Local).is_err());
+    }
+}
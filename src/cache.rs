@@ -0,0 +1,461 @@
+/*
+MIT License
+
+Copyright (c) 2024 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Local on-disk cache of fetched timelogs.
+//!
+//! Timelogs are cached per GitLab instance and [`QueryScope`], and per day. As older timelogs
+//! rarely change, only days that are not cached yet and the most recent days
+//! (see [`RECENT_DAYS`]) are fetched again by default.
+//!
+//! [`fetch_results_cached`] is the entry point.
+
+use crate::calendar::TimeZone;
+use crate::cfg::cache_dir_path;
+use crate::fetch::{GitLabApi, QueryScope, fetch_results};
+use crate::gitlab_api::types::{ResponseData, ResponseNode, ResponsePageInfo, ResponseTimelogs};
+use crate::print_warning;
+use anyhow::{Context, anyhow};
+use chrono::{NaiveDate, TimeDelta};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Version of the cache file format. Cache files of other versions are
+/// discarded.
+const CACHE_VERSION: u32 = 3;
+
+/// Number of days, including today, that are always fetched again unless
+/// running offline.
+const RECENT_DAYS: i64 = 7;
+
+/// How the cache is used.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Fetch the days that are not cached yet or recent.
+    #[default]
+    Incremental,
+    /// Fetch all days and update the cache.
+    Refresh,
+    /// Don't use the network and only use the cache.
+    Offline,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Cache {
+    version: u32,
+    /// The resolved timezone of the days, such as `Europe/Berlin`, also if
+    /// the local timezone is used. Caches of other timezones are discarded,
+    /// as their days differ.
+    timezone: String,
+    /// All nodes per day (in `timezone`). Days without timelogs are present with
+    /// an empty list, so that they are known to be fetched already.
+    days: BTreeMap<NaiveDate, Vec<ResponseNode>>,
+}
+
+impl Cache {
+    /// Returns an empty cache for the given timezone.
+    fn new(timezone: TimeZone) -> Self {
+        Self {
+            version: CACHE_VERSION,
+            timezone: timezone.resolved_name(),
+            days: BTreeMap::new(),
+        }
+    }
+}
+
+/// Returns all days of the inclusive range.
+fn days_of(start: NaiveDate, end: NaiveDate) -> impl Iterator<Item = NaiveDate> {
+    start.iter_days().take_while(move |day| *day <= end)
+}
+
+impl Cache {
+    /// Loads the cache of the given timezone. A missing or unreadable cache is
+    /// treated as empty.
+    fn load(path: &Path, timezone: TimeZone) -> Self {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Self::new(timezone),
+            Err(e) => {
+                print_warning(
+                    &format!("Failed to read cache file at {}: {e}", path.display()),
+                    0,
+                );
+                return Self::new(timezone);
+            }
+        };
+
+        match serde_json::from_str::<Self>(&content) {
+            Ok(cache)
+                if cache.version == CACHE_VERSION && cache.timezone == timezone.resolved_name() =>
+            {
+                cache
+            }
+            // Outdated or corrupt cache, or another timezone: just start over.
+            _ => Self::new(timezone),
+        }
+    }
+
+    /// Writes the cache to disk. The file is replaced atomically.
+    fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec(self)?)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Returns the inclusive ranges of days in `start..=end` that need to be
+    /// fetched from GitLab.
+    fn ranges_to_fetch(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+        today: NaiveDate,
+        mode: CacheMode,
+    ) -> Vec<(NaiveDate, NaiveDate)> {
+        let recent_begin = today - TimeDelta::days(RECENT_DAYS - 1);
+        let needs_fetch = |day: &NaiveDate| match mode {
+            CacheMode::Incremental => *day >= recent_begin || !self.days.contains_key(day),
+            CacheMode::Refresh => true,
+            CacheMode::Offline => false,
+        };
+
+        let mut ranges: Vec<(NaiveDate, NaiveDate)> = Vec::new();
+        for day in days_of(start, end).filter(needs_fetch) {
+            match ranges.last_mut() {
+                Some((_, range_end)) if range_end.succ_opt() == Some(day) => *range_end = day,
+                _ => ranges.push((day, day)),
+            }
+        }
+        ranges
+    }
+
+    /// Replaces the cached days of `start..=end` with the given nodes.
+    ///
+    /// Nodes outside the range are ignored; they will be cached once their
+    /// day is fetched.
+    fn store(
+        &mut self,
+        start: NaiveDate,
+        end: NaiveDate,
+        nodes: Vec<ResponseNode>,
+        timezone: TimeZone,
+    ) {
+        for day in days_of(start, end) {
+            self.days.insert(day, Vec::new());
+        }
+        for node in nodes {
+            let day = node.datetime(timezone);
+            if (start..=end).contains(&day) {
+                self.days.entry(day).or_default().push(node);
+            }
+        }
+    }
+
+    /// Removes the cached days of `start..=end`, so that they are fetched
+    /// again.
+    fn remove(&mut self, start: NaiveDate, end: NaiveDate) {
+        for day in days_of(start, end) {
+            self.days.remove(&day);
+        }
+    }
+
+    /// Returns the number of days in `start..=end` that are not cached.
+    fn missing_days(&self, start: NaiveDate, end: NaiveDate) -> usize {
+        days_of(start, end)
+            .filter(|day| !self.days.contains_key(day))
+            .count()
+    }
+
+    /// Returns all cached nodes of `start..=end`.
+    fn nodes(&self, start: NaiveDate, end: NaiveDate) -> Vec<ResponseNode> {
+        self.days
+            .range(start..=end)
+            .flat_map(|(_, nodes)| nodes.iter().cloned())
+            .collect()
+    }
+}
+
+/// Returns the path of the cache file for the given GitLab instance and scope.
+fn cache_file_path(base_url: &str, scope: QueryScope) -> anyhow::Result<PathBuf> {
+    let cache_dir =
+        cache_dir_path().map_err(|e| anyhow!("Failed to determine cache directory: {e}"))?;
+    Ok(cache_dir.join(cache_file_name(base_url, scope)))
+}
+
+/// Returns the name of the cache file for the given GitLab instance and scope,
+/// such as `gitlab.example.com+user+jdoe.json`.
+///
+/// Characters that are not safe in file names, including `_` and the `+`
+/// separator, are escaped as `_` followed by the hex digits of their bytes,
+/// so that distinct instances and scopes never share a file.
+fn cache_file_name(base_url: &str, scope: QueryScope) -> String {
+    let escape = |s: &str| {
+        s.bytes()
+            .map(|b| {
+                if b.is_ascii_alphanumeric() || matches!(b, b'.' | b'-') {
+                    char::from(b).to_string()
+                } else {
+                    format!("_{b:02x}")
+                }
+            })
+            .collect::<String>()
+    };
+    let instance = base_url
+        .split_once("://")
+        .map_or(base_url, |(_scheme, rest)| rest)
+        .trim_end_matches('/');
+    format!(
+        "{}+{}+{}.json",
+        escape(instance),
+        scope.kind(),
+        escape(scope.name())
+    )
+}
+
+/// Like [`fetch_results`] but only fetches the days that are not cached yet or
+/// recent, depending on `mode`. All fetched timelogs are written to the cache,
+/// except for days of partial responses, which are fetched again next time.
+///
+/// # Parameters
+/// - `scope`: Whose timelogs are fetched.
+/// - `base_url`: Base URL of the GitLab instance, such as
+///   `https://gitlab.example.com`.
+/// - `api`: Connection to the GitLab instance. Only required if something
+///   needs to be fetched.
+/// - `start_date`: Inclusive begin date.
+/// - `end_date`: Inclusive end date.
+/// - `timezone`: Timezone in which the days begin and end.
+/// - `mode`: How the cache is used.
+pub fn fetch_results_cached(
+    scope: QueryScope,
+    base_url: &str,
+    api: Option<&GitLabApi>,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    timezone: TimeZone,
+    mode: CacheMode,
+) -> anyhow::Result<ResponseData> {
+    let path = cache_file_path(base_url, scope)?;
+    let mut cache = Cache::load(&path, timezone);
+
+    let today = timezone.today();
+    let ranges = cache.ranges_to_fetch(start_date, end_date, today, mode);
+
+    if mode == CacheMode::Offline {
+        let missing = cache.missing_days(start_date, end_date);
+        if missing > 0 {
+            print_warning(
+                &format!("{missing} day(s) of the time span are not cached and thus missing."),
+                0,
+            );
+        }
+    }
+
+    let mut incomplete_ranges = Vec::new();
+    let mut error = None;
+    if !ranges.is_empty() {
+        let api = api.context("A token is required to fetch timelogs")?;
+        'ranges: for &(range_start, range_end) in &ranges {
+            for ((chunk_start, chunk_end), result) in
+                fetch_results(api, scope, range_start, range_end, timezone)
+            {
+                match result {
+                    Ok((response, partial)) => {
+                        cache.store(chunk_start, chunk_end, response.timelogs.nodes, timezone);
+                        if partial {
+                            incomplete_ranges.push((chunk_start, chunk_end));
+                        }
+                    }
+                    Err(e) => {
+                        error = Some(e);
+                        break 'ranges;
+                    }
+                }
+            }
+        }
+    }
+
+    let nodes = cache.nodes(start_date, end_date);
+    if !ranges.is_empty() {
+        // Also on errors, the cache is saved, so that the days fetched until
+        // then aren't fetched again next time.
+        for (range_start, range_end) in incomplete_ranges {
+            cache.remove(range_start, range_end);
+        }
+        if let Err(e) = cache.save(&path) {
+            print_warning(
+                &format!("Failed to write cache file at {}: {e:#}", path.display()),
+                0,
+            );
+        }
+    }
+    if let Some(error) = error {
+        return Err(error);
+    }
+
+    Ok(ResponseData {
+        timelogs: ResponseTimelogs {
+            nodes,
+            pageInfo: ResponsePageInfo {
+                hasPreviousPage: false,
+                startCursor: None,
+            },
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gitlab_api::test_utils::node;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 6, day).unwrap()
+    }
+
+    #[test]
+    fn ranges_to_fetch_skips_old_cached_days() {
+        let mut cache = Cache::new(TimeZone::Local);
+        cache.store(date(3), date(5), Vec::new(), TimeZone::Local);
+        cache.store(date(8), date(8), Vec::new(), TimeZone::Local);
+
+        // Everything from the 24th on is recent.
+        let today = date(30);
+        assert_eq!(
+            cache.ranges_to_fetch(date(1), date(10), today, CacheMode::Incremental),
+            vec![(date(1), date(2)), (date(6), date(7)), (date(9), date(10))]
+        );
+        assert_eq!(
+            cache.ranges_to_fetch(date(20), date(26), today, CacheMode::Incremental),
+            vec![(date(20), date(26))]
+        );
+        assert_eq!(
+            cache.ranges_to_fetch(date(3), date(5), today, CacheMode::Refresh),
+            vec![(date(3), date(5))]
+        );
+        assert!(
+            cache
+                .ranges_to_fetch(date(1), date(10), today, CacheMode::Offline)
+                .is_empty()
+        );
+        assert_eq!(cache.missing_days(date(1), date(10)), 6);
+    }
+
+    #[test]
+    fn store_replaces_days_of_range() {
+        let mut cache = Cache::new(TimeZone::Local);
+        cache.store(
+            date(3),
+            date(4),
+            vec![
+                node("2024-06-03T12:00:00Z", 60, "A", None, None),
+                node("2024-06-04T12:00:00Z", 60, "B", None, None),
+            ],
+            TimeZone::Local,
+        );
+        cache.store(
+            date(4),
+            date(4),
+            vec![
+                node("2024-06-04T12:00:00Z", 60, "C", None, None),
+                // Outside the range: ignored.
+                node("2024-06-05T12:00:00Z", 60, "D", None, None),
+            ],
+            TimeZone::Local,
+        );
+
+        let titles = cache
+            .nodes(date(1), date(10))
+            .iter()
+            .map(|node| node.target.title().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(titles, ["A", "C"]);
+    }
+
+    #[test]
+    fn load_discards_caches_of_other_timezones() {
+        let berlin = "Europe/Berlin".parse::<TimeZone>().unwrap();
+        let new_york = "America/New_York".parse::<TimeZone>().unwrap();
+        let path = std::env::temp_dir().join(format!(
+            "gitlab-timelogs-cache-test-{}.json",
+            std::process::id()
+        ));
+        let mut cache = Cache::new(berlin);
+        cache.store(date(3), date(3), Vec::new(), berlin);
+        cache.save(&path).unwrap();
+
+        assert_eq!(Cache::load(&path, berlin).missing_days(date(3), date(3)), 0);
+        assert_eq!(
+            Cache::load(&path, new_york).missing_days(date(3), date(3)),
+            1
+        );
+        // The local timezone is stored by its actual name.
+        assert_ne!(Cache::new(TimeZone::Local).timezone, "local");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn cache_file_names_are_unambiguous() {
+        let url = "https://gitlab.example.com";
+        assert_eq!(
+            cache_file_name(url, QueryScope::User("jdoe")),
+            "gitlab.example.com+user+jdoe.json"
+        );
+        assert_eq!(
+            cache_file_name("http://localhost:8080/gitlab/", QueryScope::Group("a/b")),
+            "localhost_3a8080_2fgitlab+group+a_2fb.json"
+        );
+
+        let names = [
+            cache_file_name(url, QueryScope::User("a_b")),
+            cache_file_name(url, QueryScope::User("a/b")),
+            cache_file_name(url, QueryScope::User("a_2fb")),
+            cache_file_name(url, QueryScope::Group("a_b")),
+            cache_file_name(url, QueryScope::Project("a_b")),
+            cache_file_name(&format!("{url}/x+user"), QueryScope::User("a")),
+            cache_file_name(url, QueryScope::User("x+user+a")),
+        ];
+        let unique = names.iter().collect::<std::collections::BTreeSet<_>>();
+        assert_eq!(unique.len(), names.len(), "{names:?}");
+    }
+
+    #[test]
+    fn cache_roundtrip() {
+        let mut cache = Cache::new(TimeZone::Local);
+        cache.store(
+            date(3),
+            date(3),
+            vec![node("2024-06-03T12:00:00Z", 60, "A", Some("E"), Some("g"))],
+            TimeZone::Local,
+        );
+
+        let json = serde_json::to_string(&cache).unwrap();
+        let cache2 = serde_json::from_str::<Cache>(&json).unwrap();
+        assert_eq!(cache.days, cache2.days);
+    }
+}
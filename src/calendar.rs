@@ -0,0 +1,285 @@
+/*
+MIT License
+
+Copyright (c) 2024 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Calendar conventions: the time zone in which days begin and end, and the
+//! first day of the week. They are used consistently for fetching, grouping,
+//! and displaying timelogs.
+
+use chrono::{
+    DateTime, Datelike, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Utc,
+    Weekday,
+};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// The time zone in which days begin and end.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimeZone {
+    /// The local time zone of the machine.
+    #[default]
+    Local,
+    /// A time zone of the IANA database, such as `America/New_York`.
+    Named(chrono_tz::Tz),
+}
+
+impl TimeZone {
+    /// Returns the current day.
+    pub fn today(self) -> NaiveDate {
+        match self {
+            Self::Local => Local::now().date_naive(),
+            Self::Named(tz) => Utc::now().with_timezone(&tz).date_naive(),
+        }
+    }
+
+    /// Returns the day of the given point in time.
+    pub fn date_of<Tz: chrono::TimeZone>(self, datetime: &DateTime<Tz>) -> NaiveDate {
+        self.naive_of(datetime).date()
+    }
+
+    /// Returns the date and time of the given point in time.
+    pub fn naive_of<Tz: chrono::TimeZone>(self, datetime: &DateTime<Tz>) -> NaiveDateTime {
+        match self {
+            Self::Local => datetime.with_timezone(&Local).naive_local(),
+            Self::Named(tz) => datetime.with_timezone(&tz).naive_local(),
+        }
+    }
+
+    /// Returns the beginning of the given day.
+    ///
+    /// If midnight doesn't exist due to a daylight saving time transition,
+    /// the first existing full hour of the day is used.
+    pub fn start_of_day(self, day: NaiveDate) -> DateTime<FixedOffset> {
+        let midnight = day.and_time(NaiveTime::MIN);
+        (0..24)
+            .find_map(|hour| {
+                let time = midnight + TimeDelta::hours(hour);
+                match self {
+                    Self::Local => time
+                        .and_local_timezone(Local)
+                        .earliest()
+                        .map(|time| time.fixed_offset()),
+                    Self::Named(tz) => time
+                        .and_local_timezone(tz)
+                        .earliest()
+                        .map(|time| time.fixed_offset()),
+                }
+            })
+            .unwrap_or_else(|| midnight.and_utc().fixed_offset())
+    }
+
+    /// Returns the name of the actual time zone, also for [`Self::Local`],
+    /// such as `Europe/Berlin`.
+    ///
+    /// If the name of the local time zone is unknown, its UTC offsets in
+    /// winter and summer are returned instead, such as `+01:00/+02:00`.
+    pub fn resolved_name(self) -> String {
+        match self {
+            Self::Local => iana_time_zone::get_timezone().unwrap_or_else(|_| {
+                let year = Local::now().year();
+                let offset = |month| {
+                    NaiveDate::from_ymd_opt(year, month, 1)
+                        .map(|day| Self::Local.start_of_day(day).offset().to_string())
+                        .unwrap_or_default()
+                };
+                format!("{}/{}", offset(1), offset(7))
+            }),
+            Self::Named(tz) => tz.name().to_string(),
+        }
+    }
+}
+
+impl FromStr for TimeZone {
+    type Err = String;
+
+    /// Parses `local` or the name of an IANA time zone, such as
+    /// `Europe/Berlin`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("local") {
+            return Ok(Self::Local);
+        }
+        s.parse::<chrono_tz::Tz>()
+            .map(Self::Named)
+            .map_err(|_| format!("unknown time zone `{s}`"))
+    }
+}
+
+impl Display for TimeZone {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Local => f.write_str("local"),
+            Self::Named(tz) => f.write_str(tz.name()),
+        }
+    }
+}
+
+/// The first day of the week.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum WeekStart {
+    #[default]
+    Monday,
+    Sunday,
+}
+
+impl WeekStart {
+    const fn weekday(self) -> Weekday {
+        match self {
+            Self::Monday => Weekday::Mon,
+            Self::Sunday => Weekday::Sun,
+        }
+    }
+}
+
+/// A week of seven days, starting on the configured first day of the week.
+///
+/// Weeks are numbered like ISO weeks. A week starting on Sunday has the
+/// number of the ISO week of its Monday.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Week {
+    first_day: NaiveDate,
+}
+
+impl Week {
+    /// Returns the week of the given day.
+    pub fn of(day: NaiveDate, start: WeekStart) -> Self {
+        let days = day.weekday().days_since(start.weekday());
+        Self {
+            first_day: day - TimeDelta::days(days.into()),
+        }
+    }
+
+    pub const fn first_day(self) -> NaiveDate {
+        self.first_day
+    }
+
+    pub fn last_day(self) -> NaiveDate {
+        self.first_day + TimeDelta::days(6)
+    }
+
+    /// Returns whether the day belongs to this week.
+    pub fn contains(self, day: NaiveDate) -> bool {
+        (self.first_day..=self.last_day()).contains(&day)
+    }
+
+    /// Returns the year the week number belongs to.
+    pub fn year(self) -> i32 {
+        self.monday().iso_week().year()
+    }
+
+    /// Returns the number of the week.
+    pub fn week(self) -> u32 {
+        self.monday().iso_week().week()
+    }
+
+    fn monday(self) -> NaiveDate {
+        self.first_day + TimeDelta::days(Weekday::Mon.days_since(self.first_day.weekday()).into())
+    }
+}
+
+impl Display for Week {
+    /// Formats the week like ISO weeks, such as `2024-W23`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-W{:02}", self.year(), self.week())
+    }
+}
+
+/// The calendar conventions of a report.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Calendar {
+    pub timezone: TimeZone,
+    pub week_start: WeekStart,
+}
+
+impl Calendar {
+    /// Returns the current day.
+    pub fn today(self) -> NaiveDate {
+        self.timezone.today()
+    }
+
+    /// Returns the week of the given day.
+    pub fn week_of(self, day: NaiveDate) -> Week {
+        Week::of(day, self.week_start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weeks_start_on_the_configured_day() {
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        // A Wednesday.
+        let day = date(6, 5);
+
+        let week = Week::of(day, WeekStart::Monday);
+        assert_eq!(week.first_day(), date(6, 3));
+        assert_eq!(week.last_day(), date(6, 9));
+        assert_eq!(week.to_string(), "2024-W23");
+
+        let week = Week::of(day, WeekStart::Sunday);
+        assert_eq!(week.first_day(), date(6, 2));
+        assert_eq!(week.last_day(), date(6, 8));
+        assert_eq!(week.to_string(), "2024-W23");
+        assert!(week.contains(date(6, 2)));
+        assert!(!week.contains(date(6, 9)));
+        assert_eq!(
+            Week::of(date(6, 9), WeekStart::Sunday).to_string(),
+            "2024-W24"
+        );
+    }
+
+    #[test]
+    fn timezones_determine_the_day() {
+        let datetime = DateTime::parse_from_rfc3339("2024-06-03T02:00:00Z").unwrap();
+        let new_york = "America/New_York".parse::<TimeZone>().unwrap();
+        let berlin = "Europe/Berlin".parse::<TimeZone>().unwrap();
+
+        assert_eq!(
+            new_york.date_of(&datetime),
+            NaiveDate::from_ymd_opt(2024, 6, 2).unwrap()
+        );
+        assert_eq!(
+            berlin.date_of(&datetime),
+            NaiveDate::from_ymd_opt(2024, 6, 3).unwrap()
+        );
+        assert_eq!(new_york.to_string(), "America/New_York");
+        assert_eq!("local".parse::<TimeZone>(), Ok(TimeZone::Local));
+        assert!("Mars/Olympus_Mons".parse::<TimeZone>().is_err());
+    }
+
+    #[test]
+    fn start_of_day_skips_missing_midnight() {
+        // Chile switches to daylight saving time at midnight.
+        let santiago = "America/Santiago".parse::<TimeZone>().unwrap();
+        let day = NaiveDate::from_ymd_opt(2024, 9, 8).unwrap();
+
+        assert_eq!(
+            santiago.start_of_day(day).to_rfc3339(),
+            "2024-09-08T01:00:00-03:00"
+        );
+    }
+}
@@ -51,6 +51,29 @@ fn config_file_path() -> Result<PathBuf, Box<dyn Error>> {
     Ok(config_dir.join("config.toml"))
 }
 
+/// Returns the directory for cached data with respect to the current OS.
+pub fn cache_dir_path() -> Result<PathBuf, Box<dyn Error>> {
+    #[cfg(target_family = "unix")]
+    let cache_os_dir = {
+        // First look for XDG_CACHE_HOME, then fall back to HOME
+        // https://specifications.freedesktop.org/basedir-spec/basedir-spec-latest.html
+        match std::env::var("XDG_CACHE_HOME") {
+            Ok(dir) => PathBuf::from(dir),
+            Err(_) => PathBuf::from(std::env::var("HOME")?).join(".cache"),
+        }
+    };
+    #[cfg(target_family = "windows")]
+    let cache_os_dir = PathBuf::from(std::env::var("LOCALAPPDATA")?);
+
+    #[cfg(target_family = "unix")]
+    let cache_dir = cache_os_dir.join("gitlab-timelogs");
+    // On Windows, config and cache share `%LOCALAPPDATA%`.
+    #[cfg(target_family = "windows")]
+    let cache_dir = cache_os_dir.join("gitlab-timelogs").join("cache");
+
+    Ok(cache_dir)
+}
+
 /// Reads the config file and parses it from TOML.
 /// On UNIX, it uses `
 fn read_config_file<T: DeserializeOwned>() -> Result<T, Box<dyn Error>> {
@@ -83,18 +106,163 @@ fn read_config_file<T: DeserializeOwned>() -> Result<T, Box<dyn Error>> {
 /// This is a workaround that clap has no built-in support for a config file
 /// that serves as source for command line options by itself. The focus is
 /// also on the natural error reporting by clap.
-pub fn get_cfg() -> Result<CliArgs, Box<dyn Error>> {
+///
+/// Returns one configuration per selected profile of the config file. This is
+/// exactly one, unless `--all-profiles` is used.
+pub fn get_cfg() -> Result<Vec<CliArgs>, Box<dyn Error>> {
     let config_content = read_config_file::<CfgFile>()?;
-    let config_args: Vec<(String, String)> = config_content.to_cli_args();
-    let mut all_args = std::env::args().collect::<Vec<_>>();
+    let cli_args = std::env::args().collect::<Vec<_>>();
+    let team = contains_option(&cli_args, "--team");
+    if team && contains_option(&cli_args, "--username") {
+        return Err("`--team` can't be combined with `--username`".into());
+    }
+
+    let profiles = if contains_option(&cli_args, "--all-profiles") {
+        let names = config_content.profile_names();
+        if names.is_empty() {
+            return Err("`--all-profiles` requires profiles in the config file".into());
+        }
+        names.into_iter().map(Some).collect()
+    } else {
+        let profile = option_value(&cli_args, "--profile")
+            .or_else(|| config_content.default_profile().map(ToString::to_string));
+        vec![profile]
+    };
+
+    let mut cfgs = Vec::new();
+    for profile in profiles {
+        let config_args = config_content.to_cli_args(profile.as_deref(), team)?;
+        let rules = config_content.rules(profile.as_deref())?;
+        let working_hours = config_content.working_hours(profile.as_deref())?;
+        let all_args = merge_config_args(cli_args.clone(), config_args);
+        let mut cfg = cli::CliArgs::parse_from(all_args);
+        let absences = config_content.absences(profile.as_deref(), cfg.calendar().timezone)?;
+        cfg.set_profile(profile);
+        cfg.set_rules(rules);
+        cfg.set_working_hours(working_hours);
+        cfg.set_absences(absences);
+        cfgs.push(cfg);
+    }
+    Ok(cfgs)
+}
+
+/// Returns the value of the option in the arguments, given either as
+/// `--opt value` or as `--opt=value`.
+fn option_value(args: &[String], opt_name: &str) -> Option<String> {
+    args.iter().enumerate().find_map(|(i, arg)| {
+        if arg == opt_name {
+            args.get(i + 1).cloned()
+        } else {
+            arg.strip_prefix(opt_name)
+                .and_then(|rest| rest.strip_prefix('='))
+                .map(ToString::to_string)
+        }
+    })
+}
+
+/// Returns whether the option is present in the arguments, either as
+/// `--opt value` or as `--opt=value`.
+fn contains_option(args: &[String], opt_name: &str) -> bool {
+    args.iter().any(|arg| {
+        arg == opt_name
+            || arg
+                .strip_prefix(opt_name)
+                .is_some_and(|rest| rest.starts_with('='))
+    })
+}
+
+/// Appends the options from the config file to the command line arguments,
+/// unless they are already present.
+fn merge_config_args(mut all_args: Vec<String>, config_args: Vec<(String, String)>) -> Vec<String> {
+    // `--host` and `--url` both select the GitLab instance. An instance from
+    // the command line must not be mixed with one from the config file.
+    let instance_opts = ["--host", "--url"];
+    let has_instance = instance_opts
+        .iter()
+        .any(|opt| contains_option(&all_args, opt));
+    // Options such as `--username` may occur multiple times in the config
+    // args, so only the original command line is relevant.
+    let cli_args = all_args.clone();
 
     // Push config options as arguments, before parsing them in clap.
     for (opt_name, opt_value) in config_args {
-        if !all_args.contains(&opt_name) {
+        let is_instance_opt = instance_opts.contains(&opt_name.as_str());
+        let is_present = contains_option(&cli_args, &opt_name) || (is_instance_opt && has_instance);
+        if !is_present {
             all_args.push(opt_name);
-            all_args.push(opt_value);
+            // Flags, such as `--ignore-case`, have no value.
+            if !opt_value.is_empty() {
+                all_args.push(opt_value);
+            }
         }
     }
+    all_args
+}
 
-    Ok(cli::CliArgs::parse_from(all_args))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn option_value_supports_both_syntaxes() {
+        let cli_args = args(&["bin", "--profile", "work", "--output=json"]);
+
+        assert_eq!(
+            option_value(&cli_args, "--profile"),
+            Some("work".to_string())
+        );
+        assert_eq!(
+            option_value(&cli_args, "--output"),
+            Some("json".to_string())
+        );
+        assert_eq!(option_value(&cli_args, "--out"), None);
+    }
+
+    #[test]
+    fn merge_config_args_prefers_command_line() {
+        let config = vec![
+            (
+                "--url".to_string(),
+                "https://example.com/gitlab".to_string(),
+            ),
+            ("--username".to_string(), "config-user".to_string()),
+        ];
+
+        assert_eq!(
+            merge_config_args(args(&["bin", "--username=cli-user"]), config.clone()),
+            args(&[
+                "bin",
+                "--username=cli-user",
+                "--url",
+                "https://example.com/gitlab"
+            ])
+        );
+        assert_eq!(
+            merge_config_args(args(&["bin", "--host", "gitlab.example.com"]), config),
+            args(&[
+                "bin",
+                "--host",
+                "gitlab.example.com",
+                "--username",
+                "config-user"
+            ])
+        );
+    }
+
+    #[test]
+    fn merge_config_args_keeps_repeated_options() {
+        let config = vec![
+            ("--username".to_string(), "alice".to_string()),
+            ("--username".to_string(), "bob".to_string()),
+        ];
+
+        assert_eq!(
+            merge_config_args(args(&["bin"]), config),
+            args(&["bin", "--username", "alice", "--username", "bob"])
+        );
+    }
 }
@@ -21,38 +21,318 @@ LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
 OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 SOFTWARE.
 */
+use crate::absences::{Absences, AbsencesConfig};
+use crate::cache::CacheMode;
+use crate::calendar::{Calendar, TimeZone, Week, WeekStart};
+use crate::fetch::{
+    DEFAULT_JOBS, DEFAULT_RETRIES, DEFAULT_TIMEOUT, GitLabApi, QueryScope, RetryPolicy,
+};
+use crate::filter::{FilterField, FilterSpec, Filters, PatternKind, parse_duration};
+use crate::gitlab_api::types::User;
+use crate::rules::{Rules, RulesConfig, Severity, rules_config_or};
+use crate::working_hours::{Workdays, WorkingHours, WorkingHoursConfig};
 use anyhow::anyhow;
-use chrono::{Datelike, Local, NaiveDate, TimeDelta, Weekday};
-use clap::Parser;
-use std::ops::{Add, Sub};
+use chrono::{Datelike, Months, NaiveDate, TimeDelta, Weekday};
+use clap::{Parser, Subcommand, ValueEnum};
+use reqwest::Url;
+use reqwest::blocking::Client;
+use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-#[derive(serde::Deserialize)]
-pub struct CfgFile {
+/// The GitLab-specific values of the config file, either at the top level
+/// or of a named profile.
+#[derive(serde::Deserialize, Debug, Default, Clone)]
+pub struct CfgProfile {
     gitlab_host: Option<String>,
+    gitlab_url: Option<String>,
     gitlab_username: Option<String>,
     gitlab_token: Option<String>,
+    /// Usernames of the members of the team, used with `--team`.
+    team: Option<Vec<String>>,
+    /// Days of the week on which work is expected, such as `["mon", "tue"]`.
+    workdays: Option<Vec<String>>,
+    /// Maximum number of concurrent requests to GitLab.
+    jobs: Option<usize>,
+    /// Number of retries of a request that failed due to a transient error.
+    retries: Option<u32>,
+    /// Timeout of a single request, such as `30s`.
+    timeout: Option<String>,
+    /// IANA timezone, such as `America/New_York`.
+    timezone: Option<String>,
+    /// First day of the week, `monday` or `sunday`.
+    week_start: Option<String>,
+    /// Local filters for the timelogs.
+    filters: Option<CfgFilters>,
+    /// Settings of the sanity-check rules.
+    rules: Option<RulesConfig>,
+    /// Target working time, for the overtime balance.
+    working_hours: Option<WorkingHoursConfig>,
+    /// Public holidays, vacation, and sick days.
+    absences: Option<AbsencesConfig>,
 }
 
-impl CfgFile {
+/// The `filters` table of the config file, equivalent to the filter options
+/// of the command line.
+#[derive(serde::Deserialize, Debug, Default, Clone)]
+pub struct CfgFilters {
+    include: Option<Vec<String>>,
+    exclude: Option<Vec<String>>,
+    ignore_case: Option<bool>,
+    min_duration: Option<String>,
+    max_duration: Option<String>,
+}
+
+impl CfgFilters {
+    /// Returns the values of `self`, with the missing keys taken from `base`.
+    fn or(self, base: &Self) -> Self {
+        Self {
+            include: self.include.or_else(|| base.include.clone()),
+            exclude: self.exclude.or_else(|| base.exclude.clone()),
+            ignore_case: self.ignore_case.or(base.ignore_case),
+            min_duration: self.min_duration.or_else(|| base.min_duration.clone()),
+            max_duration: self.max_duration.or_else(|| base.max_duration.clone()),
+        }
+    }
+
+    /// Returns the values as command line options. Flags have an empty value.
+    fn to_cli_args(&self) -> Vec<(String, String)> {
+        let mut args = Vec::new();
+        for include in self.include.iter().flatten() {
+            args.push(("--include".to_string(), include.clone()));
+        }
+        for exclude in self.exclude.iter().flatten() {
+            args.push(("--exclude".to_string(), exclude.clone()));
+        }
+        if self.ignore_case == Some(true) {
+            args.push(("--ignore-case".to_string(), String::new()));
+        }
+        if let Some(min) = &self.min_duration {
+            args.push(("--min-duration".to_string(), min.clone()));
+        }
+        if let Some(max) = &self.max_duration {
+            args.push(("--max-duration".to_string(), max.clone()));
+        }
+        args
+    }
+}
+
+/// Merges a table of a profile with the same table of the top level, using
+/// `or` for the keys of the table.
+fn merge_tables<T: Clone>(
+    table: Option<T>,
+    base: &Option<T>,
+    or: impl FnOnce(T, &T) -> T,
+) -> Option<T> {
+    match (table, base) {
+        (Some(table), Some(base)) => Some(or(table, base)),
+        (table, base) => table.or_else(|| base.clone()),
+    }
+}
+
+impl CfgProfile {
+    /// Returns the values of `self`, with the missing ones taken from `base`.
+    ///
+    /// Host and URL are treated as a unit, as they both select the instance.
+    /// The tables `filters`, `rules`, `working_hours`, and `absences` are
+    /// merged key by key, so that a profile only needs to set what differs.
+    fn or(self, base: &Self) -> Self {
+        let has_instance = self.gitlab_host.is_some() || self.gitlab_url.is_some();
+        Self {
+            gitlab_host: if has_instance {
+                self.gitlab_host
+            } else {
+                base.gitlab_host.clone()
+            },
+            gitlab_url: if has_instance {
+                self.gitlab_url
+            } else {
+                base.gitlab_url.clone()
+            },
+            gitlab_username: self
+                .gitlab_username
+                .or_else(|| base.gitlab_username.clone()),
+            gitlab_token: self.gitlab_token.or_else(|| base.gitlab_token.clone()),
+            team: self.team.or_else(|| base.team.clone()),
+            workdays: self.workdays.or_else(|| base.workdays.clone()),
+            jobs: self.jobs.or(base.jobs),
+            retries: self.retries.or(base.retries),
+            timeout: self.timeout.or_else(|| base.timeout.clone()),
+            timezone: self.timezone.or_else(|| base.timezone.clone()),
+            week_start: self.week_start.or_else(|| base.week_start.clone()),
+            filters: merge_tables(self.filters, &base.filters, CfgFilters::or),
+            rules: merge_tables(self.rules, &base.rules, rules_config_or),
+            working_hours: merge_tables(
+                self.working_hours,
+                &base.working_hours,
+                WorkingHoursConfig::or,
+            ),
+            absences: merge_tables(self.absences, &base.absences, AbsencesConfig::or),
+        }
+    }
+
+    /// Returns the values as command line options. If `team` is set, the
+    /// members of the team are used as usernames.
     #[allow(clippy::wrong_self_convention)]
-    pub fn to_cli_args(self) -> Vec<(String, String)> {
+    fn to_cli_args(self, team: bool) -> Result<Vec<(String, String)>, String> {
         let mut args = Vec::new();
         if let Some(host) = self.gitlab_host {
             args.push(("--host".to_string(), host));
         }
-        if let Some(username) = self.gitlab_username {
+        if let Some(url) = self.gitlab_url {
+            args.push(("--url".to_string(), url));
+        }
+        let usernames = if team {
+            self.team
+                .ok_or("`--team` requires a `team` list in the config file")?
+        } else {
+            self.gitlab_username.into_iter().collect()
+        };
+        for username in usernames {
             args.push(("--username".to_string(), username));
         }
         if let Some(token) = self.gitlab_token {
             args.push(("--token".to_string(), token));
         }
-        args
+        if let Some(workdays) = self.workdays {
+            args.push(("--workdays".to_string(), workdays.join(",")));
+        }
+        if let Some(jobs) = self.jobs {
+            args.push(("--jobs".to_string(), jobs.to_string()));
+        }
+        if let Some(retries) = self.retries {
+            args.push(("--retries".to_string(), retries.to_string()));
+        }
+        if let Some(timeout) = self.timeout {
+            args.push(("--timeout".to_string(), timeout));
+        }
+        if let Some(timezone) = self.timezone {
+            args.push(("--timezone".to_string(), timezone));
+        }
+        if let Some(week_start) = self.week_start {
+            args.push(("--week-start".to_string(), week_start));
+        }
+        if let Some(filters) = self.filters {
+            args.extend(filters.to_cli_args());
+        }
+        Ok(args)
     }
 }
 
+#[derive(serde::Deserialize, Debug, Default)]
+pub struct CfgFile {
+    /// Values that apply if no profile is selected. They also serve as
+    /// fallback for the values of all profiles.
+    #[serde(flatten)]
+    base: CfgProfile,
+    /// Profile that is used if `--profile` is not specified.
+    default_profile: Option<String>,
+    #[serde(default)]
+    profiles: BTreeMap<String, CfgProfile>,
+}
+
+impl CfgFile {
+    pub fn default_profile(&self) -> Option<&str> {
+        self.default_profile.as_deref()
+    }
+
+    /// Returns the names of all profiles in alphabetical order.
+    pub fn profile_names(&self) -> Vec<String> {
+        self.profiles.keys().cloned().collect()
+    }
+
+    /// Returns the values of the given profile, or of the top level if no
+    /// profile is given.
+    fn values(&self, profile: Option<&str>) -> Result<CfgProfile, String> {
+        let Some(name) = profile else {
+            return Ok(self.base.clone());
+        };
+        self.profiles
+            .get(name)
+            .map(|values| values.clone().or(&self.base))
+            .ok_or_else(|| {
+                format!(
+                    "unknown profile `{name}`, available profiles: {}",
+                    self.profile_names().join(", ")
+                )
+            })
+    }
+
+    /// Returns the options of the given profile, or of the top level if no
+    /// profile is given. If `team` is set, the members of the team are used as
+    /// usernames.
+    pub fn to_cli_args(
+        &self,
+        profile: Option<&str>,
+        team: bool,
+    ) -> Result<Vec<(String, String)>, String> {
+        self.values(profile)?.to_cli_args(team)
+    }
+
+    /// Returns the sanity-check rules of the given profile, or of the top
+    /// level if no profile is given.
+    pub fn rules(&self, profile: Option<&str>) -> Result<Rules, String> {
+        self.values(profile)?.rules.map_or_else(
+            || Ok(Rules::default()),
+            |config| Rules::from_config(&config),
+        )
+    }
+
+    /// Returns the working hours of the given profile, or of the top level if
+    /// no profile is given.
+    pub fn working_hours(&self, profile: Option<&str>) -> Result<Option<WorkingHours>, String> {
+        self.values(profile)?
+            .working_hours
+            .map(|config| WorkingHours::from_config(&config))
+            .transpose()
+    }
+
+    /// Returns the absences of the given profile, or of the top level if no
+    /// profile is given. This reads the referenced iCalendar files, whose
+    /// times are converted to `timezone`.
+    pub fn absences(&self, profile: Option<&str>, timezone: TimeZone) -> Result<Absences, String> {
+        self.values(profile)?.absences.map_or_else(
+            || Ok(Absences::default()),
+            |config| Absences::from_config(&config, timezone),
+        )
+    }
+}
+
+/// Supported output formats.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Colored and human-readable output for the terminal.
+    #[default]
+    Text,
+    /// A single JSON document with all timelogs and their aggregates.
+    Json,
+    /// CSV with one row per timelog, e.g., for spreadsheets.
+    Csv,
+    /// Markdown report, e.g., for wikis or merge request descriptions.
+    Markdown,
+    /// Self-contained HTML report.
+    Html,
+}
+
+/// Subcommands. Without a subcommand, the timesheet is shown.
+#[derive(Subcommand, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Check the timelogs against the sanity-check rules and print a compact
+    /// list of findings instead of the timesheet, e.g., for CI or cron jobs.
+    ///
+    /// Exits with code 2 if there are findings of at least the `--fail-on`
+    /// severity.
+    Check {
+        /// The minimum severity of findings that make the check fail.
+        #[arg(long, value_name = "SEVERITY", value_enum, default_value_t = Severity::Warn)]
+        fail_on: Severity,
+    },
+}
+
 /// CLI Arguments for `clap`. If not present, the values are taken from
 /// environment variables.
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(
     version,
     about = "\
@@ -64,56 +344,233 @@ a configuration file. The configuration file must be placed under
 following structure:
 
 gitlab_host = \"gitlab.example.com\"
+# Alternatively, the full base URL of the GitLab instance:
+# gitlab_url = \"https://example.com:8443/gitlab\"
 gitlab_username = \"<user>\"
 gitlab_token = \"<token>\"
 
+Multiple GitLab instances can be configured as named profiles, which are
+selected with `--profile`:
+
+default_profile = \"work\"
+
+[profiles.work]
+gitlab_host = \"gitlab.example.com\"
+gitlab_username = \"<user>\"
+gitlab_token = \"<token>\"
+
+The usernames of a team, which are used with `--team`, are configured as
+follows:
+
+team = [\"alice\", \"bob\"]
+
 
 gitlab-timelogs IS NOT associated with the official GitLab project!"
 )]
 pub struct CliArgs {
     /// The GitLab host without `https://`. For example `gitlab.example.com`.
-    #[arg(long = "host", env)]
-    gitlab_host: String,
-    /// Your GitLab username.
-    #[arg(long = "username", env)]
-    gitlab_username: String,
+    ///
+    /// Use `--url` for instances with a non-standard port or path prefix.
+    /// One of both is required.
+    #[arg(long = "host", env, global = true)]
+    gitlab_host: Option<String>,
+    /// The full base URL of the GitLab instance, including the scheme and
+    /// optionally a port and path prefix. For example
+    /// `https://example.com:8443/gitlab`.
+    ///
+    /// This has a higher precedence than `--host`.
+    #[arg(long = "url", env = "GITLAB_URL", global = true)]
+    gitlab_url: Option<String>,
+    /// Your GitLab username. Defaults to the owner of the token.
+    ///
+    /// Can be specified multiple times to compare the timelogs of a team.
+    #[arg(long = "username", env, global = true)]
+    gitlab_username: Vec<String>,
     /// Token with read access (scope `read_api`) to GitLab API. You can get one
-    /// on `https://<gitlab_host>/-/user_settings/personal_access_tokens`.
-    #[arg(long = "token", env)]
-    gitlab_token: String,
+    /// on `<gitlab_url>/-/user_settings/personal_access_tokens`, for example
+    /// `https://gitlab.example.com/-/user_settings/personal_access_tokens`.
+    ///
+    /// Not required with `--offline` or `--from-file`.
+    #[arg(long = "token", env, global = true)]
+    gitlab_token: Option<String>,
     /// Filter for oldest date (begin, inclusive). For example `2024-06-01`.
-    /// If unspecified, this defaults to the beginning of the week (Monday,
-    /// local time).
+    /// If unspecified, this defaults to the beginning of the current week
+    /// (see `--week-start` and `--timezone`).
     ///
     /// Must be no more than `--before`.
-    #[arg(long = "after", alias = "begin", default_value_t = get_default_after_date())]
-    gitlab_after: NaiveDate,
+    #[arg(long = "after", alias = "begin", global = true)]
+    gitlab_after: Option<NaiveDate>,
     /// Filter for newest date (end, inclusive). For example `2024-06-30`.
-    /// If unspecified, this defaults to the end of the week (Sunday, local
-    /// time).
+    /// If unspecified, this defaults to the end of the current week (see
+    /// `--week-start` and `--timezone`).
     ///
     /// Must be no less than `--after`.
-    #[arg(long = "before", alias = "end", default_value_t = get_default_before_date())]
-    gitlab_before: NaiveDate,
+    #[arg(long = "before", alias = "end", global = true)]
+    gitlab_before: Option<NaiveDate>,
     /// Show the whole month.
     ///
     /// This has a higher precedence than `--after` and `--before`.
-    #[arg(long = "month")]
+    #[arg(long = "month", global = true)]
     show_month: bool,
+    /// Time span as range expression, such as `last-week`, `this-month`,
+    /// `last-month`, `yesterday`, `2024-W23`, `2024-06`, `2024-Q2`, `2024`,
+    /// or `-7d..today`.
+    ///
+    /// Both ends of a range `<from>..<to>` are expressions themselves. Offsets
+    /// such as `-7d`, `-2w`, or `-1m` are relative to today, and an empty end
+    /// means today.
+    #[arg(
+        long,
+        value_name = "RANGE",
+        conflicts_with_all = ["gitlab_after", "gitlab_before", "show_month"],
+        global = true
+    )]
+    range: Option<String>,
+    /// Timezone in which days begin and end, as name of the IANA time zone
+    /// database, such as `America/New_York`. Defaults to the local timezone.
+    #[arg(long, value_name = "TZ", global = true)]
+    timezone: Option<TimeZone>,
+    /// First day of the week, for the default time span and the grouping
+    /// by week.
+    #[arg(long, value_name = "DAY", value_enum, global = true)]
+    week_start: Option<WeekStart>,
+    /// Days of the week on which work is expected, as comma-separated list,
+    /// such as `mon,tue,wed,thu`. Missing days are only reported on
+    /// workdays. Defaults to Monday to Friday.
+    #[arg(long, value_name = "DAYS", global = true)]
+    workdays: Option<Workdays>,
     /// Show an extended summary at the end with the time per issue and per
     /// epic.
-    #[arg(short = 'x', long = "extended-summary")]
+    #[arg(short = 'x', long = "extended-summary", global = true)]
     print_extended_summary: bool,
     /// When specified, restricts results to groups whose full path (e.g.,
     /// `team-x/project-y`) contains the given value or matches it exactly.
     ///
-    /// The filter is case-sensitive.
-    #[arg(long)]
+    /// This is a shorthand for `--include group:<value>`.
+    #[arg(long, global = true)]
     filter_group: Option<String>,
+    /// Only show timelogs matching the filter. Can be specified multiple
+    /// times: filters of the same field are alternatives, filters of
+    /// different fields must all match.
+    ///
+    /// The format is `<field>:<pattern>`, with the fields `group`,
+    /// `project`, `epic`, `title`, `summary`, and `user`. The pattern
+    /// matches as substring, as regular expression with a `re:` prefix, or
+    /// as glob with a `glob:` prefix, such as `summary:re:^Meeting` or
+    /// `title:glob:*Review*`.
+    #[arg(long, value_name = "FILTER", global = true)]
+    include: Vec<FilterSpec>,
+    /// Hide timelogs matching the filter. Can be specified multiple times.
+    /// The format is the same as for `--include`.
+    #[arg(long, value_name = "FILTER", global = true)]
+    exclude: Vec<FilterSpec>,
+    /// Match the patterns of `--include`, `--exclude`, and `--filter-group`
+    /// case-insensitively.
+    #[arg(long, global = true)]
+    ignore_case: bool,
+    /// Hide timelogs shorter than the given duration, such as `15m` or
+    /// `1h30m`.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, global = true)]
+    min_duration: Option<Duration>,
+    /// Hide timelogs longer than the given duration, such as `4h`.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, global = true)]
+    max_duration: Option<Duration>,
+    /// Show the timelogs of everyone in the group with the given full path,
+    /// such as `team-x`, instead of the timelogs of a user.
+    #[arg(long, value_name = "FULL_PATH", conflicts_with_all = ["project", "team"], global = true)]
+    group: Option<String>,
+    /// Show the timelogs of everyone in the project with the given full path,
+    /// such as `team-x/project-y`, instead of the timelogs of a user.
+    #[arg(long, value_name = "FULL_PATH", conflicts_with = "team", global = true)]
+    project: Option<String>,
+    /// The output format. `json` emits a single machine-readable document
+    /// with all timelogs and the aggregates per week, day, epic, and issue.
+    /// `csv` emits one row per timelog. `markdown` and `html` emit a report
+    /// with tables per day, totals, and the summary per epic and per issue.
+    #[arg(long = "output", value_enum, default_value_t = OutputFormat::Text, global = true)]
+    output: OutputFormat,
+    /// Fetch all days of the time span again, instead of only the days that
+    /// are not cached yet or of the last 7 days.
+    #[arg(long, conflicts_with = "offline", global = true)]
+    refresh: bool,
+    /// Don't access the network and only show cached timelogs.
+    #[arg(long, global = true)]
+    offline: bool,
+    /// Render the GraphQL responses saved with `--save-response` instead of
+    /// fetching from GitLab. Neither the network nor the cache is used.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["offline", "refresh", "save_response"], global = true)]
+    from_file: Option<PathBuf>,
+    /// Save the raw GraphQL responses of the whole time span to the given
+    /// file, e.g., to reproduce issues with `--from-file`. The cache is
+    /// bypassed in that case.
+    #[arg(long, value_name = "PATH", conflicts_with = "offline", global = true)]
+    save_response: Option<PathBuf>,
+    /// Maximum number of concurrent requests to GitLab. Long time spans are
+    /// fetched in chunks of 31 days. Defaults to 4.
+    #[arg(long, value_name = "N", global = true)]
+    jobs: Option<NonZeroUsize>,
+    /// Number of retries of a request to GitLab that failed due to a network
+    /// error, a timeout, or an overloaded or rate-limiting server. Retries
+    /// back off exponentially or wait as long as GitLab requests. Defaults
+    /// to 3; `0` disables retries.
+    #[arg(long, value_name = "N", global = true)]
+    retries: Option<u32>,
+    /// Timeout of a single request to GitLab, such as `30s` or `2m`. Plain
+    /// numbers are seconds. Defaults to `30s`.
+    #[arg(long, value_name = "DURATION", value_parser = parse_timeout, global = true)]
+    timeout: Option<Duration>,
+    /// Use the given profile of the config file instead of the default
+    /// profile.
+    #[arg(long, value_name = "NAME", global = true)]
+    profile: Option<String>,
+    /// Fetch the timelogs of all profiles of the config file and show them
+    /// in a combined timesheet with a grand total.
+    #[arg(long, conflicts_with_all = ["profile", "from_file", "save_response"], global = true)]
+    all_profiles: bool,
+    /// Display name of the owner of the token, looked up at runtime.
+    #[arg(skip)]
+    display_name: Option<String>,
+    /// The sanity-check rules, taken from the config file.
+    #[arg(skip)]
+    rules: Rules,
+    /// The target working time, taken from the config file.
+    #[arg(skip)]
+    working_hours: Option<WorkingHours>,
+    /// Holidays, vacation, and sick days, taken from the config file.
+    #[arg(skip)]
+    absences: Absences,
+    /// Fetch the timelogs of all members of the `team` list of the config
+    /// file and show them in a combined timesheet.
+    #[arg(long, conflicts_with_all = ["from_file", "save_response"], global = true)]
+    team: bool,
+    /// Write the output to the given file instead of stdout.
+    ///
+    /// The `text` output is written without colors in that case.
+    #[arg(long = "output-file", global = true)]
+    output_file: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
 impl CliArgs {
     pub fn validate(&self) -> Result<(), anyhow::Error> {
+        // Checked here instead of by clap, as clap also enforces required
+        // global options in the context of subcommands.
+        if self.gitlab_host.is_none() && self.gitlab_url.is_none() {
+            return Err(anyhow!("either `--host` or `--url` is required"));
+        }
+        if self.gitlab_token.is_none() && !self.offline && self.from_file.is_none() {
+            return Err(anyhow!(
+                "`--token` is required unless `--offline` or `--from-file` is used"
+            ));
+        }
+
+        if let Some(expr) = &self.range {
+            let calendar = self.calendar();
+            DateRange::parse(expr, calendar.today(), calendar.week_start)
+                .map_err(|e| anyhow!(e))?;
+        }
+
         if self.after() > self.before() {
             return Err(anyhow!(
                 "invalid date range: `--after` ({}) must be earlier than `--before` ({})",
@@ -122,36 +579,160 @@ impl CliArgs {
             ));
         };
 
+        self.filters()?;
+
+        if self.gitlab_username.len() > 1
+            && (self.from_file.is_some() || self.save_response.is_some())
+        {
+            return Err(anyhow!(
+                "multiple usernames can't be combined with `--from-file` or `--save-response`"
+            ));
+        }
+
+        if let Some(url) = &self.gitlab_url {
+            let parsed = Url::parse(url).map_err(|e| anyhow!("invalid GitLab URL `{url}`: {e}"))?;
+            if !matches!(parsed.scheme(), "http" | "https") {
+                return Err(anyhow!(
+                    "invalid GitLab URL `{url}`: scheme must be `http` or `https`"
+                ));
+            }
+        }
+
         Ok(())
     }
 }
 
 impl CliArgs {
+    /// Returns the host, or the base URL if only `--url` was given.
     pub fn host(&self) -> &str {
-        &self.gitlab_host
+        self.gitlab_host
+            .as_deref()
+            .or(self.gitlab_url.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// Returns the base URL of the GitLab instance, such as
+    /// `https://gitlab.example.com`.
+    pub fn base_url(&self) -> String {
+        match (&self.gitlab_url, &self.gitlab_host) {
+            (Some(url), _) => url.trim_end_matches('/').to_string(),
+            (None, Some(host)) => format!("https://{host}"),
+            (None, None) => String::new(),
+        }
     }
 
-    pub fn username(&self) -> &str {
+    /// Returns the (first) username, either the configured one or the one of
+    /// the owner of the token, once it has been looked up.
+    pub fn username(&self) -> Option<&str> {
+        self.gitlab_username.first().map(String::as_str)
+    }
+
+    /// Returns all configured usernames.
+    pub fn usernames(&self) -> &[String] {
         &self.gitlab_username
     }
 
-    pub fn token(&self) -> &str {
-        &self.gitlab_token
+    /// Returns whose timelogs are queried, unless the username is not known
+    /// yet.
+    pub fn scope(&self) -> Option<QueryScope<'_>> {
+        match (&self.project, &self.group) {
+            (Some(project), _) => Some(QueryScope::Project(project)),
+            (None, Some(group)) => Some(QueryScope::Group(group)),
+            (None, None) => self.username().map(QueryScope::User),
+        }
     }
 
-    pub fn before(&self) -> NaiveDate {
-        if self.show_month {
-            get_month_end()
-        } else {
-            self.gitlab_before
+    /// Returns whether the timelogs of a group or project are queried instead
+    /// of the timelogs of a user.
+    pub const fn is_namespace_scope(&self) -> bool {
+        self.group.is_some() || self.project.is_some()
+    }
+
+    /// Splits the config into one config per username, e.g., to fetch the
+    /// timelogs of each member of a team.
+    pub fn split_by_user(self) -> Vec<Self> {
+        if self.gitlab_username.len() <= 1 || self.is_namespace_scope() {
+            return vec![self];
         }
+        self.gitlab_username
+            .iter()
+            .map(|username| Self {
+                gitlab_username: vec![username.clone()],
+                ..self.clone()
+            })
+            .collect()
+    }
+
+    /// Returns the display name of the user, if it has been looked up.
+    pub fn display_name(&self) -> Option<&str> {
+        self.display_name.as_deref()
+    }
+
+    /// Sets the owner of the token. The username is only taken if none was
+    /// configured.
+    pub fn set_current_user(&mut self, user: User) {
+        if self.gitlab_username.is_empty() {
+            self.gitlab_username.push(user.username);
+        }
+        self.display_name = Some(user.name);
+    }
+
+    pub fn token(&self) -> Option<&str> {
+        self.gitlab_token.as_deref()
+    }
+
+    /// Returns the connection to the GitLab instance, or `None` without a
+    /// token. The HTTP client is shared with other connections.
+    pub fn api(&self, client: &Client) -> Option<GitLabApi> {
+        let token = self.token()?;
+        let jobs = self.jobs.unwrap_or(DEFAULT_JOBS);
+        let retry = RetryPolicy {
+            retries: self.retries.unwrap_or(DEFAULT_RETRIES),
+            timeout: self.timeout.unwrap_or(DEFAULT_TIMEOUT),
+        };
+        Some(GitLabApi::new(
+            client.clone(),
+            self.base_url(),
+            token.to_string(),
+            jobs,
+            retry,
+        ))
+    }
+
+    pub fn before(&self) -> NaiveDate {
+        self.date_range().before
     }
 
     pub fn after(&self) -> NaiveDate {
-        if self.show_month {
-            get_month_begin()
-        } else {
-            self.gitlab_after
+        self.date_range().after
+    }
+
+    /// Returns the selected time span.
+    fn date_range(&self) -> DateRange {
+        let calendar = self.calendar();
+        // Invalid ranges are rejected by `validate`.
+        let range = self
+            .range
+            .as_deref()
+            .and_then(|expr| DateRange::parse(expr, calendar.today(), calendar.week_start).ok());
+        match range {
+            Some(range) => range,
+            None if self.show_month => get_month_range(calendar),
+            None => {
+                let default = get_default_range(calendar);
+                DateRange {
+                    after: self.gitlab_after.unwrap_or(default.after),
+                    before: self.gitlab_before.unwrap_or(default.before),
+                }
+            }
+        }
+    }
+
+    /// Returns the timezone and the first day of the week.
+    pub fn calendar(&self) -> Calendar {
+        Calendar {
+            timezone: self.timezone.unwrap_or_default(),
+            week_start: self.week_start.unwrap_or_default(),
         }
     }
 
@@ -159,70 +740,320 @@ impl CliArgs {
         self.print_extended_summary
     }
 
-    pub fn filter_group(&self) -> Option<&str> {
-        self.filter_group.as_deref()
+    /// Returns the local filters of the timelogs.
+    pub fn filters(&self) -> anyhow::Result<Filters> {
+        let group = self.filter_group.iter().map(|pattern| FilterSpec {
+            field: FilterField::Group,
+            kind: PatternKind::Substring,
+            pattern: pattern.clone(),
+        });
+        let includes = self
+            .include
+            .iter()
+            .cloned()
+            .chain(group)
+            .collect::<Vec<_>>();
+        Filters::new(
+            &includes,
+            &self.exclude,
+            self.ignore_case,
+            self.min_duration,
+            self.max_duration,
+        )
+    }
+
+    pub const fn cache_mode(&self) -> CacheMode {
+        if self.offline {
+            CacheMode::Offline
+        } else if self.refresh {
+            CacheMode::Refresh
+        } else {
+            CacheMode::Incremental
+        }
+    }
+
+    pub const fn output(&self) -> OutputFormat {
+        self.output
+    }
+
+    pub fn replay_file(&self) -> Option<&Path> {
+        self.from_file.as_deref()
+    }
+
+    pub fn save_response(&self) -> Option<&Path> {
+        self.save_response.as_deref()
+    }
+
+    /// Returns the name of the effective profile of the config file, if any.
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// Sets the name of the effective profile, which may differ from
+    /// `--profile` due to `default_profile` or `--all-profiles`.
+    pub fn set_profile(&mut self, profile: Option<String>) {
+        self.profile = profile;
+    }
+
+    pub const fn command(&self) -> Option<Command> {
+        self.command
+    }
+
+    pub const fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    pub const fn set_rules(&mut self, rules: Rules) {
+        self.rules = rules;
+    }
+
+    pub fn workdays(&self) -> Workdays {
+        self.workdays.unwrap_or_default()
+    }
+
+    pub const fn working_hours(&self) -> Option<&WorkingHours> {
+        self.working_hours.as_ref()
+    }
+
+    pub const fn set_working_hours(&mut self, working_hours: Option<WorkingHours>) {
+        self.working_hours = working_hours;
+    }
+
+    pub const fn absences(&self) -> &Absences {
+        &self.absences
+    }
+
+    pub fn set_absences(&mut self, absences: Absences) {
+        self.absences = absences;
+    }
+
+    pub const fn all_profiles(&self) -> bool {
+        self.all_profiles
+    }
+
+    pub fn output_file(&self) -> Option<&Path> {
+        self.output_file.as_deref()
     }
 }
 
-/// Returns the default `--before` date for [`CliArgs`].
-///
-/// Returns the previous next Sunday or today, if today is a Sunday.
-/// This makes sense as one typically wants to see what one has done in the
-/// current week.
-fn get_default_before_date() -> NaiveDate {
-    let now = Local::now();
-    let mut day = now;
-    while day.weekday() != Weekday::Sun {
-        day = day.add(TimeDelta::days(1));
+/// Parses the `--timeout` of requests. Unlike other durations, plain numbers
+/// are seconds, and the timeout must not be zero.
+fn parse_timeout(s: &str) -> Result<Duration, String> {
+    let timeout = match s.parse::<u64>() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(_) => parse_duration(s)?,
+    };
+    if timeout.is_zero() {
+        return Err(format!("timeout `{s}` must be longer than zero"));
     }
-    day.naive_local().date()
+    Ok(timeout)
 }
 
-/// Returns the default `--after` date for [`CliArgs`].
+/// Returns the default time span for [`CliArgs`]: the current week.
 ///
-/// Returns the previous Monday or today, if today is a Monday.
 /// This makes sense as one typically wants to see what one has done in the
 /// current week.
-fn get_default_after_date() -> NaiveDate {
-    let now = Local::now();
-    let mut day = now;
-    while day.weekday() != Weekday::Mon {
-        day = day.sub(TimeDelta::days(1));
+fn get_default_range(calendar: Calendar) -> DateRange {
+    let week = calendar.week_of(calendar.today());
+    DateRange {
+        after: week.first_day(),
+        before: week.last_day(),
     }
-    day.naive_local().date()
 }
 
-/// Returns the first day of the current month.
-fn get_month_begin() -> NaiveDate {
-    let now = Local::now();
-    let now_date = now.date_naive();
-    now_date.with_day(1).unwrap()
+/// Returns the current month, as selected by `--month`.
+fn get_month_range(calendar: Calendar) -> DateRange {
+    let today = calendar.today();
+    DateRange::month(today.year(), today.month()).unwrap_or_else(|| DateRange::day(today))
 }
 
-/// Returns the last day of the current month.
-fn get_month_end() -> NaiveDate {
-    let now = Local::now();
-    let now_date = now.date_naive();
-    now_date
-        .with_day(now_date.num_days_in_month() as u32)
-        .unwrap()
+/// Inclusive range of days, as selected by `--range`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DateRange {
+    pub after: NaiveDate,
+    pub before: NaiveDate,
+}
+
+impl DateRange {
+    /// Parses a range expression, such as `last-month` or `-7d..today`.
+    /// Relative expressions are resolved against `today`.
+    fn parse(expr: &str, today: NaiveDate, week_start: WeekStart) -> Result<Self, String> {
+        let range = match expr.split_once("..") {
+            Some((from, to)) => {
+                let after = Self::parse_single(from, today, week_start)?.after;
+                let before = if to.trim().is_empty() {
+                    today
+                } else {
+                    Self::parse_single(to, today, week_start)?.before
+                };
+                Self { after, before }
+            }
+            None => Self::parse_single(expr, today, week_start)?,
+        };
+        if range.after > range.before {
+            return Err(format!(
+                "range `{expr}` ends ({}) before it begins ({})",
+                range.before, range.after
+            ));
+        }
+        Ok(range)
+    }
+
+    /// Parses a single expression without `..`.
+    fn parse_single(expr: &str, today: NaiveDate, week_start: WeekStart) -> Result<Self, String> {
+        let expr = expr.trim();
+        let lowercase = expr.to_ascii_lowercase();
+        let range = match lowercase.as_str() {
+            "today" => Some(Self::day(today)),
+            "yesterday" => today.pred_opt().map(Self::day),
+            "this-week" => Some(Self::week(Week::of(today, week_start))),
+            "last-week" => Some(Self::week(Week::of(
+                today - TimeDelta::weeks(1),
+                week_start,
+            ))),
+            "this-month" => Self::month(today.year(), today.month()),
+            "last-month" => today
+                .checked_sub_months(Months::new(1))
+                .and_then(|day| Self::month(day.year(), day.month())),
+            "this-quarter" => Self::quarter(today.year(), today.month0() / 3 + 1),
+            "last-quarter" => today
+                .checked_sub_months(Months::new(3))
+                .and_then(|day| Self::quarter(day.year(), day.month0() / 3 + 1)),
+            "this-year" => Self::year(today.year()),
+            "last-year" => Self::year(today.year() - 1),
+            _ => Self::offset(&lowercase, today).or_else(|| Self::absolute(&lowercase, week_start)),
+        };
+        range.ok_or_else(|| format!("invalid date range `{expr}`"))
+    }
+
+    /// Parses an offset relative to `today`, such as `-7d` or `+1w`.
+    fn offset(expr: &str, today: NaiveDate) -> Option<Self> {
+        let (negative, rest) = match expr.as_bytes().first()? {
+            b'-' => (true, &expr[1..]),
+            b'+' => (false, &expr[1..]),
+            _ => return None,
+        };
+        let unit = rest.chars().last()?;
+        let amount = rest[..rest.len() - unit.len_utf8()].parse::<u32>().ok()?;
+        let day = match (unit, negative) {
+            ('d', true) => today.checked_sub_signed(TimeDelta::days(amount.into()))?,
+            ('d', false) => today.checked_add_signed(TimeDelta::days(amount.into()))?,
+            ('w', true) => today.checked_sub_signed(TimeDelta::weeks(amount.into()))?,
+            ('w', false) => today.checked_add_signed(TimeDelta::weeks(amount.into()))?,
+            ('m', true) => today.checked_sub_months(Months::new(amount))?,
+            ('m', false) => today.checked_add_months(Months::new(amount))?,
+            ('y', true) => today.checked_sub_months(Months::new(amount.checked_mul(12)?))?,
+            ('y', false) => today.checked_add_months(Months::new(amount.checked_mul(12)?))?,
+            _ => return None,
+        };
+        Some(Self::day(day))
+    }
+
+    /// Parses an absolute expression, such as `2024-06-03`, `2024-W23`,
+    /// `2024-06`, `2024-Q2`, or `2024`.
+    fn absolute(expr: &str, week_start: WeekStart) -> Option<Self> {
+        if let Ok(day) = NaiveDate::parse_from_str(expr, "%Y-%m-%d") {
+            return Some(Self::day(day));
+        }
+        let (year, rest) = expr.split_once('-').unwrap_or((expr, ""));
+        if year.len() != 4 {
+            return None;
+        }
+        let year = year.parse::<i32>().ok()?;
+        if rest.is_empty() {
+            Self::year(year)
+        } else if let Some(week) = rest.strip_prefix('w') {
+            let monday = NaiveDate::from_isoywd_opt(year, week.parse().ok()?, Weekday::Mon)?;
+            Some(Self::week(Week::of(monday, week_start)))
+        } else if let Some(quarter) = rest.strip_prefix('q') {
+            Self::quarter(year, quarter.parse().ok()?)
+        } else {
+            Self::month(year, rest.parse().ok()?)
+        }
+    }
+
+    const fn day(day: NaiveDate) -> Self {
+        Self {
+            after: day,
+            before: day,
+        }
+    }
+
+    fn week(week: Week) -> Self {
+        Self {
+            after: week.first_day(),
+            before: week.last_day(),
+        }
+    }
+
+    fn month(year: i32, month: u32) -> Option<Self> {
+        let after = NaiveDate::from_ymd_opt(year, month, 1)?;
+        let before = after.with_day(after.num_days_in_month().into())?;
+        Some(Self { after, before })
+    }
+
+    fn quarter(year: i32, quarter: u32) -> Option<Self> {
+        if !(1..=4).contains(&quarter) {
+            return None;
+        }
+        let after = Self::month(year, quarter * 3 - 2)?.after;
+        let before = Self::month(year, quarter * 3)?.before;
+        Some(Self { after, before })
+    }
+
+    fn year(year: i32) -> Option<Self> {
+        Some(Self {
+            after: NaiveDate::from_ymd_opt(year, 1, 1)?,
+            before: NaiveDate::from_ymd_opt(year, 12, 31)?,
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rules::RuleId;
     use chrono::NaiveDate;
 
     fn base_args(after: NaiveDate, before: NaiveDate) -> CliArgs {
         CliArgs {
-            gitlab_host: "gitlab.example.com".into(),
-            gitlab_username: "user".into(),
-            gitlab_token: "token".into(),
-            gitlab_after: after,
-            gitlab_before: before,
+            gitlab_host: Some("gitlab.example.com".into()),
+            gitlab_url: None,
+            gitlab_username: vec!["user".into()],
+            display_name: None,
+            rules: Rules::default(),
+            working_hours: None,
+            absences: Absences::default(),
+            gitlab_token: Some("token".into()),
+            gitlab_after: Some(after),
+            gitlab_before: Some(before),
             show_month: false,
+            range: None,
+            timezone: None,
+            week_start: None,
             print_extended_summary: false,
+            workdays: None,
             filter_group: None,
+            include: vec![],
+            exclude: vec![],
+            ignore_case: false,
+            min_duration: None,
+            max_duration: None,
+            refresh: false,
+            offline: false,
+            from_file: None,
+            save_response: None,
+            jobs: None,
+            retries: None,
+            timeout: None,
+            profile: None,
+            all_profiles: false,
+            team: false,
+            group: None,
+            project: None,
+            output: OutputFormat::Text,
+            output_file: None,
+            command: None,
         }
     }
 
@@ -269,4 +1100,385 @@ mod tests {
         args.show_month = true;
         assert!(args.validate().is_ok());
     }
+
+    #[test]
+    fn date_range_expressions() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        // A Wednesday.
+        let today = date(2024, 7, 3);
+        let range = |expr: &str| {
+            DateRange::parse(expr, today, WeekStart::Monday)
+                .map(|range| (range.after, range.before))
+        };
+
+        assert_eq!(range("today"), Ok((today, today)));
+        assert_eq!(range("yesterday"), Ok((date(2024, 7, 2), date(2024, 7, 2))));
+        assert_eq!(range("this-week"), Ok((date(2024, 7, 1), date(2024, 7, 7))));
+        assert_eq!(
+            range("last-week"),
+            Ok((date(2024, 6, 24), date(2024, 6, 30)))
+        );
+        assert_eq!(
+            range("last-month"),
+            Ok((date(2024, 6, 1), date(2024, 6, 30)))
+        );
+        assert_eq!(
+            range("this-quarter"),
+            Ok((date(2024, 7, 1), date(2024, 9, 30)))
+        );
+        assert_eq!(
+            range("last-year"),
+            Ok((date(2023, 1, 1), date(2023, 12, 31)))
+        );
+        assert_eq!(range("2024-W23"), Ok((date(2024, 6, 3), date(2024, 6, 9))));
+        assert_eq!(range("2024-02"), Ok((date(2024, 2, 1), date(2024, 2, 29))));
+        assert_eq!(range("2024-Q2"), Ok((date(2024, 4, 1), date(2024, 6, 30))));
+        assert_eq!(range("2024"), Ok((date(2024, 1, 1), date(2024, 12, 31))));
+        assert_eq!(range("-7d..today"), Ok((date(2024, 6, 26), today)));
+        assert_eq!(range("2024-06.."), Ok((date(2024, 6, 1), today)));
+        assert_eq!(
+            range("2024-W23..2024-06-12"),
+            Ok((date(2024, 6, 3), date(2024, 6, 12)))
+        );
+
+        assert!(range("today..-1d").is_err());
+        assert!(range("2024-Q5").is_err());
+        assert!(range("2024-13").is_err());
+        assert!(range("next-week").is_err());
+        assert!(range("7d").is_err());
+    }
+
+    #[test]
+    fn range_conflicts_with_explicit_dates() {
+        let parse = |args: &[&str]| {
+            CliArgs::try_parse_from(
+                [
+                    "gitlab-timelogs",
+                    "--host",
+                    "gitlab.example.com",
+                    "--token",
+                    "t",
+                ]
+                .iter()
+                .chain(args),
+            )
+        };
+
+        let args = parse(&["--range", "2024-06"]).unwrap();
+        assert_eq!(args.after(), NaiveDate::from_ymd_opt(2024, 6, 1).unwrap());
+        assert_eq!(args.before(), NaiveDate::from_ymd_opt(2024, 6, 30).unwrap());
+
+        assert!(parse(&["--range", "2024-06", "--after", "2024-06-02"]).is_err());
+        assert!(parse(&["--range", "2024-06", "--month"]).is_err());
+
+        let args = parse(&["--range", "2024-W23", "--week-start", "sunday"]).unwrap();
+        assert_eq!(args.after(), NaiveDate::from_ymd_opt(2024, 6, 2).unwrap());
+        assert_eq!(args.before(), NaiveDate::from_ymd_opt(2024, 6, 8).unwrap());
+
+        let args = parse(&["--range", "next-week"]).unwrap();
+        assert!(args.validate().is_err());
+    }
+
+    #[test]
+    fn base_url_prefers_url_over_host() {
+        let date = NaiveDate::from_ymd_opt(2026, 2, 1).unwrap();
+        let mut args = base_args(date, date);
+        assert_eq!(args.base_url(), "https://gitlab.example.com");
+
+        args.gitlab_url = Some("http://example.com:8080/gitlab/".into());
+        assert_eq!(args.base_url(), "http://example.com:8080/gitlab");
+        assert_eq!(args.host(), "gitlab.example.com");
+
+        args.gitlab_host = None;
+        assert_eq!(args.host(), "http://example.com:8080/gitlab/");
+    }
+
+    #[test]
+    fn validate_requires_instance_and_token() {
+        let date = NaiveDate::from_ymd_opt(2026, 2, 1).unwrap();
+        let mut args = base_args(date, date);
+        args.gitlab_token = None;
+        assert!(args.validate().is_err());
+
+        args.offline = true;
+        assert!(args.validate().is_ok());
+
+        args.gitlab_host = None;
+        assert!(args.validate().is_err());
+    }
+
+    #[test]
+    fn validate_rejects_invalid_urls() {
+        let date = NaiveDate::from_ymd_opt(2026, 2, 1).unwrap();
+        let mut args = base_args(date, date);
+
+        args.gitlab_url = Some("gitlab.example.com".into());
+        assert!(args.validate().is_err());
+
+        args.gitlab_url = Some("ftp://gitlab.example.com".into());
+        assert!(args.validate().is_err());
+
+        args.gitlab_url = Some("http://localhost:8080/gitlab".into());
+        assert!(args.validate().is_ok());
+    }
+
+    #[test]
+    fn cfg_file_profiles_fall_back_to_top_level() {
+        let cfg = toml::from_str::<CfgFile>(
+            r#"
+            gitlab_host = "gitlab.example.com"
+            gitlab_username = "user"
+            gitlab_token = "token"
+            default_profile = "work"
+
+            [profiles.work]
+            gitlab_token = "work-token"
+
+            [profiles.customer]
+            gitlab_url = "https://customer.example/gitlab"
+            gitlab_username = "customer-user"
+            "#,
+        )
+        .unwrap();
+
+        let pairs = |args: Vec<(String, String)>| {
+            args.iter()
+                .map(|(k, v)| format!("{k}={v}"))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(cfg.default_profile(), Some("work"));
+        assert_eq!(cfg.profile_names(), ["customer", "work"]);
+        assert_eq!(
+            pairs(cfg.to_cli_args(None, false).unwrap()),
+            [
+                "--host=gitlab.example.com",
+                "--username=user",
+                "--token=token"
+            ]
+        );
+        assert_eq!(
+            pairs(cfg.to_cli_args(Some("work"), false).unwrap()),
+            [
+                "--host=gitlab.example.com",
+                "--username=user",
+                "--token=work-token"
+            ]
+        );
+        assert_eq!(
+            pairs(cfg.to_cli_args(Some("customer"), false).unwrap()),
+            [
+                "--url=https://customer.example/gitlab",
+                "--username=customer-user",
+                "--token=token"
+            ]
+        );
+        assert!(cfg.to_cli_args(Some("unknown"), false).is_err());
+        assert!(cfg.to_cli_args(None, true).is_err());
+    }
+
+    #[test]
+    fn cfg_file_team_is_used_as_usernames() {
+        let cfg = toml::from_str::<CfgFile>(
+            r#"
+            gitlab_host = "gitlab.example.com"
+            gitlab_username = "lead"
+            team = ["alice", "bob"]
+            "#,
+        )
+        .unwrap();
+
+        let usernames = |team| {
+            cfg.to_cli_args(None, team)
+                .unwrap()
+                .into_iter()
+                .filter(|(k, _)| k == "--username")
+                .map(|(_, v)| v)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(usernames(false), ["lead"]);
+        assert_eq!(usernames(true), ["alice", "bob"]);
+    }
+
+    #[test]
+    fn cfg_file_filters_are_used_as_options() {
+        let cfg = toml::from_str::<CfgFile>(
+            r#"
+            gitlab_host = "gitlab.example.com"
+
+            [filters]
+            exclude = ["summary:re:^Meeting", "group:private"]
+            ignore_case = true
+            min_duration = "15m"
+
+            [profiles.work]
+            gitlab_host = "gitlab.work.example"
+            "#,
+        )
+        .unwrap();
+
+        let filter_args = cfg
+            .to_cli_args(Some("work"), false)
+            .unwrap()
+            .into_iter()
+            .filter(|(k, _)| k != "--host")
+            .collect::<Vec<_>>();
+        assert_eq!(
+            filter_args,
+            [
+                ("--exclude".to_string(), "summary:re:^Meeting".to_string()),
+                ("--exclude".to_string(), "group:private".to_string()),
+                ("--ignore-case".to_string(), String::new()),
+                ("--min-duration".to_string(), "15m".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn cfg_file_calendar_of_profiles() {
+        let cfg = toml::from_str::<CfgFile>(
+            r#"
+            gitlab_host = "gitlab.example.com"
+            week_start = "sunday"
+
+            [profiles.customer]
+            timezone = "America/New_York"
+            "#,
+        )
+        .unwrap();
+
+        let args = cfg.to_cli_args(Some("customer"), false).unwrap();
+        assert!(args.contains(&("--timezone".to_string(), "America/New_York".to_string())));
+        assert!(args.contains(&("--week-start".to_string(), "sunday".to_string())));
+    }
+
+    #[test]
+    fn parse_timeout_uses_seconds_and_rejects_zero() {
+        assert_eq!(parse_timeout("45"), Ok(Duration::from_secs(45)));
+        assert_eq!(parse_timeout("2m"), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_timeout("1m30s"), Ok(Duration::from_secs(90)));
+        assert!(parse_timeout("0").is_err());
+        assert!(parse_timeout("0s").is_err());
+        assert!(parse_timeout("30x").is_err());
+    }
+
+    #[test]
+    fn cfg_file_retries_of_profiles() {
+        let cfg = toml::from_str::<CfgFile>(
+            r#"
+            gitlab_host = "gitlab.example.com"
+            retries = 5
+
+            [profiles.vpn]
+            timeout = "2m"
+            "#,
+        )
+        .unwrap();
+
+        let args = cfg.to_cli_args(Some("vpn"), false).unwrap();
+        assert!(args.contains(&("--retries".to_string(), "5".to_string())));
+        assert!(args.contains(&("--timeout".to_string(), "2m".to_string())));
+    }
+
+    #[test]
+    fn cfg_file_tables_of_profiles_are_merged_key_by_key() {
+        let cfg = toml::from_str::<CfgFile>(
+            r#"
+            [filters]
+            exclude = ["summary:re:^Meeting"]
+            min_duration = "15m"
+
+            [working_hours]
+            weekly = "40h"
+            friday = "4h"
+
+            [absences]
+            holidays = ["2024-12-25"]
+
+            [profiles.customer]
+            filters.min_duration = "5m"
+            working_hours.daily = "6h"
+            absences.vacation = ["2024-08-01"]
+            "#,
+        )
+        .unwrap();
+
+        let args = cfg.to_cli_args(Some("customer"), false).unwrap();
+        assert_eq!(
+            args,
+            [
+                ("--exclude".to_string(), "summary:re:^Meeting".to_string()),
+                ("--min-duration".to_string(), "5m".to_string()),
+            ]
+        );
+
+        // `daily` replaces `weekly`, but `friday` is kept.
+        let hours = |hours: u64| Duration::from_secs(hours * 60 * 60);
+        let working_hours = cfg.working_hours(Some("customer")).unwrap().unwrap();
+        let workdays = Workdays::default();
+        let monday = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        let friday = NaiveDate::from_ymd_opt(2024, 6, 7).unwrap();
+        assert_eq!(working_hours.target(monday, workdays), hours(6));
+        assert_eq!(working_hours.target(friday, workdays), hours(4));
+
+        let absences = cfg.absences(Some("customer"), TimeZone::Local).unwrap();
+        let day = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        assert!(absences.get(day(12, 25)).is_some());
+        assert!(absences.get(day(8, 1)).is_some());
+    }
+
+    #[test]
+    fn cfg_file_rules_of_profiles() {
+        let cfg = toml::from_str::<CfgFile>(
+            r#"
+            [rules.long_day]
+            threshold = "12h"
+
+            [profiles.work]
+            gitlab_host = "gitlab.example.com"
+
+            [profiles.on-call]
+            gitlab_host = "gitlab.example.com"
+            rules.weekend.enabled = false
+            rules.long_day.severity = "error"
+
+            [profiles.broken]
+            rules.weekend.threshold = "1h"
+            "#,
+        )
+        .unwrap();
+
+        let mut expected = Rules::from_config(&RulesConfig::from([(
+            RuleId::LongDay,
+            toml::from_str("threshold = \"12h\"").unwrap(),
+        )]))
+        .unwrap();
+        assert_eq!(cfg.rules(None), Ok(expected.clone()));
+        assert_eq!(cfg.rules(Some("work")), Ok(expected.clone()));
+
+        // The rules of profiles are merged with the top-level rules.
+        expected = Rules::from_config(&RulesConfig::from([
+            (
+                RuleId::LongDay,
+                toml::from_str("threshold = \"12h\"\nseverity = \"error\"").unwrap(),
+            ),
+            (RuleId::Weekend, toml::from_str("enabled = false").unwrap()),
+        ]))
+        .unwrap();
+        assert_eq!(cfg.rules(Some("on-call")), Ok(expected));
+        assert!(cfg.rules(Some("broken")).is_err());
+    }
+
+    #[test]
+    fn split_by_user_creates_one_config_per_user() {
+        let date = NaiveDate::from_ymd_opt(2026, 2, 1).unwrap();
+        let mut args = base_args(date, date);
+        assert_eq!(args.clone().split_by_user().len(), 1);
+
+        args.gitlab_username = vec!["alice".into(), "bob".into()];
+        let cfgs = args.split_by_user();
+        let usernames = cfgs.iter().map(|cfg| cfg.username()).collect::<Vec<_>>();
+        assert_eq!(usernames, [Some("alice"), Some("bob")]);
+    }
 }
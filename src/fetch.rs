@@ -26,184 +26,740 @@ SOFTWARE.
 //!
 //! [`fetch_results`] is the entry point.
 
-use crate::gitlab_api::types::{ResponseData, ResponseSerialized};
-use anyhow::Context;
-use chrono::{DateTime, Local, NaiveDate, NaiveTime};
+use crate::calendar::TimeZone;
+use crate::filter::Filters;
+use crate::gitlab_api::types::{
+    CurrentUserResponseSerialized, GraphQLErrorsResponse, Response, ResponseData, ResponsePageInfo,
+    ResponseSerialized, User,
+};
+use crate::print_warning;
+use anyhow::{Context, anyhow};
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
 use reqwest::StatusCode;
 use reqwest::blocking::Client;
-use reqwest::header::AUTHORIZATION;
-use serde_json::json;
+use reqwest::header::{AUTHORIZATION, HeaderMap, RETRY_AFTER};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::error::Error;
+use std::io::IsTerminal;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
 
-const GRAPHQL_TEMPLATE: &str = include_str!("./gitlab-query.graphql");
+const GRAPHQL_QUERY: &str = include_str!("./gitlab-query.graphql");
+const GRAPHQL_QUERY_CURRENT_USER: &str = include_str!("./gitlab-current-user.graphql");
 
-fn http_error_message(status: StatusCode, url: &str) -> String {
+/// Maximum number of timelogs per page.
+const PAGE_SIZE: u32 = 500;
+
+/// Default maximum number of concurrent requests.
+pub const DEFAULT_JOBS: NonZeroUsize = NonZeroUsize::new(4).unwrap();
+
+/// Default number of retries of a request that failed due to a transient
+/// error.
+pub const DEFAULT_RETRIES: u32 = 3;
+
+/// Default timeout of a single request, including reading the response body.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Delay before the first retry. It is doubled for every further retry.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Upper bound of the exponential backoff.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Upper bound of a delay requested by GitLab via `Retry-After` or the
+/// `RateLimit-*` headers.
+const MAX_REQUESTED_DELAY: Duration = Duration::from_secs(300);
+
+/// Number of days that are fetched as one chunk. Chunks are fetched
+/// concurrently.
+const CHUNK_DAYS: i64 = 31;
+
+/// Whose timelogs are queried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryScope<'a> {
+    /// All timelogs of the user with the given username.
+    User(&'a str),
+    /// All timelogs in the group with the given full path, by anyone.
+    Group(&'a str),
+    /// All timelogs in the project with the given full path, by anyone.
+    Project(&'a str),
+}
+
+impl QueryScope<'_> {
+    /// Returns the kind of the scope: `user`, `group`, or `project`.
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            Self::User(_) => "user",
+            Self::Group(_) => "group",
+            Self::Project(_) => "project",
+        }
+    }
+
+    /// Returns the username or the full path of the group or project.
+    pub const fn name(&self) -> &str {
+        match self {
+            Self::User(name) | Self::Group(name) | Self::Project(name) => name,
+        }
+    }
+
+    /// Returns the JSON pointer to the page info of the timelogs in a
+    /// response of the query for this scope.
+    const fn page_info_pointer(&self) -> &'static str {
+        match self {
+            Self::User(_) => "/data/timelogs/pageInfo",
+            Self::Group(_) => "/data/group/timelogs/pageInfo",
+            Self::Project(_) => "/data/project/timelogs/pageInfo",
+        }
+    }
+}
+
+/// Returns the URL of the page where users can create access tokens.
+fn token_settings_url(base_url: &str) -> String {
+    format!(
+        "{}/-/user_settings/personal_access_tokens",
+        base_url.trim_end_matches('/')
+    )
+}
+
+fn http_error_message(status: StatusCode, url: &str, base_url: &str) -> String {
     match status {
         StatusCode::UNAUTHORIZED => format!(
-            "GitLab API request failed with 401 Unauthorized for {url}.\nCheck that the configured token is valid."
+            "GitLab API request failed with 401 Unauthorized for {url}.\nCheck that the configured token is valid. You can create one at {}.",
+            token_settings_url(base_url)
         ),
         StatusCode::FORBIDDEN => format!(
-            "GitLab API request failed with 403 Forbidden for {url}.\nCheck that the configured token has `read_api` permission."
+            "GitLab API request failed with 403 Forbidden for {url}.\nCheck that the configured token has `read_api` permission. You can create one at {}.",
+            token_settings_url(base_url)
         ),
         _ => format!("GitLab API request failed with {status} for {url}."),
     }
 }
 
-/// Transforms a [`NaiveDate`] to a `DateTime<Local>`.
-fn naive_date_to_local_datetime(date: NaiveDate) -> DateTime<Local> {
-    date.and_time(NaiveTime::MIN)
-        .and_local_timezone(Local)
-        .unwrap()
+/// Returns the URL of the GraphQL endpoint of the GitLab instance.
+fn graphql_url(base_url: &str) -> String {
+    format!("{}/api/graphql", base_url.trim_end_matches('/'))
+}
+
+/// Builds the JSON payload for the GraphQL query.
+///
+/// All dynamic values are passed as GraphQL variables, so that they don't need
+/// any escaping.
+fn graphql_payload(
+    scope: QueryScope,
+    before: Option<&str>,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    timezone: TimeZone,
+) -> Value {
+    let (operation_name, scope_variable) = match scope {
+        QueryScope::User(_) => ("timelogs", "username"),
+        QueryScope::Group(_) => ("groupTimelogs", "fullPath"),
+        QueryScope::Project(_) => ("projectTimelogs", "fullPath"),
+    };
+    json!({
+        "query": GRAPHQL_QUERY,
+        "operationName": operation_name,
+        "variables": {
+            scope_variable: scope.name(),
+            "pageSize": PAGE_SIZE,
+            "before": before,
+            // GitLab API ignores the time component and just looks at the
+            // date and the timezone.
+            "startDate": timezone.start_of_day(start_date).to_rfc3339(),
+            "endDate": timezone.start_of_day(end_date).to_rfc3339(),
+        },
+    })
+}
+
+/// How requests to GitLab are retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt. `0` disables retries.
+    pub retries: u32,
+    /// Timeout of a single attempt.
+    pub timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: DEFAULT_RETRIES,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+/// A failed attempt of a request.
+#[derive(Debug)]
+enum AttemptError {
+    /// The request may succeed when it is sent again, optionally after the
+    /// delay requested by GitLab.
+    Transient {
+        reason: String,
+        delay: Option<Duration>,
+    },
+    /// Sending the request again won't help.
+    Fatal(anyhow::Error),
+}
+
+/// Returns the error with all of its causes, such as
+/// `error sending request: connection refused`.
+fn error_chain(error: &dyn Error) -> String {
+    let mut chain = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        chain.push_str(": ");
+        chain.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    chain
+}
+
+/// Returns whether a response with the given status is worth a retry, as
+/// GitLab or a proxy in front of it is overloaded or rate-limits us.
+fn is_transient_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Returns the delay until the next attempt that GitLab requests via the
+/// `Retry-After` header, or via the `RateLimit-Reset` header once
+/// `RateLimit-Remaining` reached zero. The delay is capped at
+/// [`MAX_REQUESTED_DELAY`].
+///
+/// # Parameters
+/// - `headers`: Headers of the failed response.
+/// - `now`: Current time, to resolve absolute points in time.
+fn requested_delay(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+    let header = |name: &str| headers.get(name)?.to_str().ok().map(str::trim);
+    let until = |time: DateTime<Utc>| (time - now).to_std().unwrap_or_default();
+
+    // `Retry-After` is either a number of seconds or an HTTP date.
+    let retry_after = header(RETRY_AFTER.as_str()).and_then(|value| {
+        value
+            .parse::<u64>()
+            .map(Duration::from_secs)
+            .ok()
+            .or_else(|| {
+                DateTime::parse_from_rfc2822(value)
+                    .ok()
+                    .map(|time| until(time.to_utc()))
+            })
+    });
+    // `RateLimit-Reset` is a Unix timestamp.
+    let rate_limit_reset = || {
+        header("ratelimit-remaining")
+            .filter(|remaining| *remaining == "0")
+            .and_then(|_| header("ratelimit-reset"))
+            .and_then(|reset| reset.parse::<i64>().ok())
+            .and_then(|reset| DateTime::from_timestamp(reset, 0))
+            .map(until)
+    };
+
+    retry_after
+        .or_else(rate_limit_reset)
+        .map(|delay| delay.min(MAX_REQUESTED_DELAY))
+}
+
+/// Returns the exponential backoff before the given retry, starting at `1`.
+fn backoff(retry: u32) -> Duration {
+    INITIAL_BACKOFF
+        .saturating_mul(2_u32.saturating_pow(retry.saturating_sub(1)))
+        .min(MAX_BACKOFF)
+}
+
+/// Connection to the GraphQL API of a GitLab instance.
+///
+/// The HTTP client is shared by all requests, so that connections are kept
+/// alive and reused.
+#[derive(Debug, Clone)]
+pub struct GitLabApi {
+    client: Client,
+    /// Base URL of the GitLab instance, such as `https://gitlab.example.com`.
+    base_url: String,
+    /// GitLab token to access the GitLab instance. Must have at least READ
+    /// access.
+    token: String,
+    /// Maximum number of concurrent requests.
+    jobs: NonZeroUsize,
+    /// How failed requests are retried.
+    retry: RetryPolicy,
+}
+
+impl GitLabApi {
+    /// Creates a connection that uses the given HTTP client. Clones of a
+    /// client share its connection pool.
+    pub const fn new(
+        client: Client,
+        base_url: String,
+        token: String,
+        jobs: NonZeroUsize,
+        retry: RetryPolicy,
+    ) -> Self {
+        Self {
+            client,
+            base_url,
+            token,
+            jobs,
+            retry,
+        }
+    }
+
+    /// Sends the GraphQL payload to the GitLab API and returns the raw JSON
+    /// response.
+    ///
+    /// Network errors, timeouts, and responses that indicate an overloaded or
+    /// rate-limiting server are retried according to the [`RetryPolicy`],
+    /// with exponential backoff or the delay requested by GitLab.
+    fn post_graphql(&self, payload: &Value) -> anyhow::Result<Value> {
+        let url = graphql_url(&self.base_url);
+        let mut failures = Vec::new();
+        loop {
+            let (reason, delay) = match self.try_post_graphql(&url, payload) {
+                Ok(response) => return Ok(response),
+                Err(AttemptError::Fatal(e)) => return Err(e),
+                Err(AttemptError::Transient { reason, delay }) => (reason, delay),
+            };
+
+            let retry = u32::try_from(failures.len()).unwrap_or(u32::MAX) + 1;
+            if retry > self.retry.retries {
+                failures.push(reason);
+                break;
+            }
+            let delay = delay.unwrap_or_else(|| backoff(retry));
+            print_warning(
+                &format!(
+                    "GitLab API request failed: {reason}. Retrying in {}s ({retry}/{}).",
+                    delay.as_secs_f32().ceil(),
+                    self.retry.retries
+                ),
+                0,
+            );
+            failures.push(format!(
+                "{reason}, retried after {}s",
+                delay.as_secs_f32().ceil()
+            ));
+            std::thread::sleep(delay);
+        }
+
+        let attempts = failures
+            .iter()
+            .enumerate()
+            .map(|(i, failure)| format!("\n  attempt {}: {failure}", i + 1))
+            .collect::<String>();
+        Err(anyhow!(
+            "GitLab API request to {url} failed after {} attempt(s):{attempts}",
+            failures.len()
+        ))
+    }
+
+    /// Performs a single attempt of [`Self::post_graphql`].
+    fn try_post_graphql(&self, url: &str, payload: &Value) -> Result<Value, AttemptError> {
+        let authorization = format!("Bearer {}", self.token);
+        let transient = |e: reqwest::Error| {
+            let reason = if e.is_timeout() {
+                format!("no response within {}s", self.retry.timeout.as_secs_f32())
+            } else {
+                error_chain(&e)
+            };
+            AttemptError::Transient {
+                reason,
+                delay: None,
+            }
+        };
+
+        let plain_response = self
+            .client
+            .post(url)
+            .header(AUTHORIZATION, authorization)
+            .timeout(self.retry.timeout)
+            .json(payload)
+            .send()
+            .map_err(|e| {
+                if e.is_builder() || e.is_redirect() {
+                    AttemptError::Fatal(anyhow::Error::new(e).context("Failed to send request"))
+                } else {
+                    transient(e)
+                }
+            })?;
+
+        let status = plain_response.status();
+        if is_transient_status(status) {
+            return Err(AttemptError::Transient {
+                reason: status.to_string(),
+                delay: requested_delay(plain_response.headers(), Utc::now()),
+            });
+        }
+        if !status.is_success() {
+            return Err(AttemptError::Fatal(anyhow!(
+                "{}",
+                http_error_message(status, plain_response.url().as_str(), &self.base_url)
+            )));
+        }
+
+        let body = plain_response.bytes().map_err(transient)?;
+        serde_json::from_slice::<Value>(&body)
+            .context("Failed to parse response body as JSON")
+            .map_err(AttemptError::Fatal)
+    }
 }
 
 /// Performs a single request against the GitLab API, getting exactly one page
-/// of the paged data source.
+/// of the paged data source as raw JSON. Use [`parse_response_page`] to get the
+/// typed data.
 ///
 /// The data is filtered for the date span to make the request smaller and
-/// thus much quicker. Other filters are applied locally after the server
-/// response has been fetched.
+/// thus much quicker. Other filters are applied locally by
+/// [`response_data_apply_filters`] after the server response has been fetched.
 ///
 /// # Parameters
-/// - `username`: The exact GitLab username of the user.
-/// - `host`: Host name of the GitLab instance without `https://`
-/// - `token`: GitLab token to access the GitLab instance. Must have at least
-///   READ access.
+/// - `api`: Connection to the GitLab instance.
+/// - `scope`: Whose timelogs are fetched.
 /// - `before`: Identifier from previous request to get the next page of the
 ///   paginated result.
 /// - `start_date`: Inclusive begin date.
 /// - `end_date`: Inclusive end date.
-/// - `filter_group`: Optional group filter, such as `Engineering/ProjectA`.
-///   If provided, the filter must be contained in the full group name of the
-///   entry.
-fn fetch_response_data(
-    username: &str,
-    host: &str,
-    token: &str,
+/// - `timezone`: Timezone in which the days begin and end.
+fn fetch_response_page(
+    api: &GitLabApi,
+    scope: QueryScope,
     before: Option<&str>,
     start_date: NaiveDate,
     end_date: NaiveDate,
-    filter_group: Option<&str>,
-) -> anyhow::Result<ResponseData> {
-    let graphql_query = GRAPHQL_TEMPLATE
-        .replace("%USERNAME%", username)
-        .replace("%BEFORE%", before.unwrap_or_default())
-        // GitLab API ignores the time component and just looks at the
-        // date and the timezone.
-        .replace(
-            "%START_DATE%",
-            naive_date_to_local_datetime(start_date)
-                .to_string()
-                .as_str(),
-        )
-        // GitLab API ignores the time component and just looks at the
-        // date and the timezone.
-        .replace(
-            "%END_DATE%",
-            naive_date_to_local_datetime(end_date).to_string().as_str(),
-        );
-    let payload = json!({ "query": graphql_query });
-
-    let authorization = format!("Bearer {token}");
-    let url = format!("https://{host}/api/graphql");
-    let client = Client::new();
-
-    let plain_response = client
-        .post(url)
-        .header(AUTHORIZATION, authorization)
-        .json(&payload)
-        .send()
-        .context("Failed to send request")?;
-
-    let status = plain_response.status();
-    if !status.is_success() {
-        anyhow::bail!(
-            "{}",
-            http_error_message(status, plain_response.url().as_str())
-        );
+    timezone: TimeZone,
+) -> anyhow::Result<Value> {
+    let payload = graphql_payload(scope, before, start_date, end_date, timezone);
+    api.post_graphql(&payload)
+}
+
+/// Fetches the owner of the token.
+///
+/// Returns `None` if GitLab doesn't associate a user with the token.
+pub fn fetch_current_user(api: &GitLabApi) -> anyhow::Result<Option<User>> {
+    let payload = json!({ "query": GRAPHQL_QUERY_CURRENT_USER });
+    let response = api.post_graphql(&payload)?;
+    parse_current_user(response)
+}
+
+fn parse_current_user(response: Value) -> anyhow::Result<Option<User>> {
+    let response = serde_json::from_value::<CurrentUserResponseSerialized>(response)
+        .context("Failed to parse response body as JSON")?;
+    if let Some(errors) = response.errors {
+        return Err(errors.into());
     }
+    Ok(response.data.and_then(|data| data.currentUser))
+}
 
-    let response = plain_response
-        .json::<ResponseSerialized>()
-        .context("Failed to parse response body as JSON")
-        .map(|x| x.into_typed());
+/// Parses a single raw page of the GraphQL response.
+///
+/// For partial responses, i.e., data together with errors, the errors are
+/// returned next to the data.
+fn parse_response_page(
+    page: Value,
+) -> anyhow::Result<(ResponseData, Option<GraphQLErrorsResponse>)> {
+    let response = serde_json::from_value::<ResponseSerialized>(page)
+        .context("Failed to parse response body as JSON")?;
 
-    let response_data = response?.into_result()?;
-    let response_data_filtered = response_data_apply_filters(response_data, filter_group);
-    Ok(response_data_filtered)
+    match response.into_typed() {
+        Response::Data(data) => Ok((data, None)),
+        Response::Partial(data, errors) => Ok((data, Some(errors))),
+        Response::Errors(errors) => Err(errors.into()),
+        Response::Inaccessible(kind) => Err(anyhow!(
+            "The {kind} doesn't exist or is not accessible with the token"
+        )),
+        Response::Empty => Err(anyhow!(
+            "GitLab returned a response without data and without errors"
+        )),
+    }
 }
 
 /// Applies local filters onto the response nodes and returns a filtered object.
-fn response_data_apply_filters(
-    mut response: ResponseData,
-    filter_group: Option<&str>,
-) -> ResponseData /* filtered */ {
-    // We look for each node if it passes all filters. Otherwise, we remove it.
-    response.timelogs.nodes.retain(|node| {
-        // For each filter: we remove not matching items
-        if let (Some(filter), Some(group)) = (filter_group, &node.project.group) {
-            // case-sensitive search
-            let has_group = group.fullName.contains(filter) || group.fullPath.contains(filter);
-            if !has_group {
-                return false;
-            }
+///
+/// Filtered-out timelogs are removed, so they are not part of any aggregate.
+///
+/// # Parameters
+/// - `filters`: The include, exclude, and duration filters, see [`Filters`].
+pub fn response_data_apply_filters(mut response: ResponseData, filters: &Filters) -> ResponseData /* filtered */
+{
+    response.timelogs.nodes.retain(|node| filters.matches(node));
+    response
+}
+
+/// Fetches all pages of a single chunk of the time span, following the
+/// pagination.
+fn fetch_chunk_pages(
+    api: &GitLabApi,
+    scope: QueryScope,
+    (start_date, end_date): (NaiveDate, NaiveDate),
+    timezone: TimeZone,
+) -> anyhow::Result<Vec<Value>> {
+    let mut pages = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = fetch_response_page(
+            api,
+            scope,
+            cursor.as_deref(),
+            start_date,
+            end_date,
+            timezone,
+        )?;
+        // Only the page info is needed here, the page itself is parsed once
+        // all pages are fetched. Without page info, GitLab responded with
+        // errors, which are reported then.
+        let page_info = page
+            .pointer(scope.page_info_pointer())
+            .map(ResponsePageInfo::deserialize)
+            .transpose()
+            .context("Failed to parse the page info of the response")?;
+        pages.push(page);
+
+        let Some(page_info) = page_info.filter(|info| info.hasPreviousPage) else {
+            break;
+        };
+        let start_cursor = page_info
+            .startCursor
+            .context("GitLab reported a previous page but no cursor to fetch it")?;
+        cursor = Some(start_cursor);
+    }
+    Ok(pages)
+}
+
+/// Splits the inclusive range of days into chunks of at most [`CHUNK_DAYS`]
+/// days.
+fn chunks(start_date: NaiveDate, end_date: NaiveDate) -> Vec<(NaiveDate, NaiveDate)> {
+    let mut chunks = Vec::new();
+    let mut chunk_start = start_date;
+    while chunk_start <= end_date {
+        let chunk_end = chunk_start
+            .checked_add_signed(TimeDelta::days(CHUNK_DAYS - 1))
+            .map_or(end_date, |day| day.min(end_date));
+        chunks.push((chunk_start, chunk_end));
+        match chunk_end.succ_opt() {
+            Some(next) => chunk_start = next,
+            None => break,
         }
+    }
+    chunks
+}
+
+/// Progress of fetching multiple chunks, shown on stderr if it is a terminal.
+#[derive(Debug)]
+struct Progress {
+    total: usize,
+    done: AtomicUsize,
+    visible: bool,
+}
+
+impl Progress {
+    fn new(total: usize) -> Self {
+        let progress = Self {
+            total,
+            done: AtomicUsize::new(0),
+            visible: total > 1 && std::io::stderr().is_terminal(),
+        };
+        progress.print(0);
+        progress
+    }
 
-        // We retain everything that was not filtered out.
-        true
+    /// Marks one more chunk as done.
+    fn advance(&self) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        self.print(done);
+    }
+
+    fn print(&self, done: usize) {
+        if self.visible {
+            eprint!("\rFetching timelogs: {done}/{} chunks", self.total);
+        }
+    }
+
+    /// Removes the progress from the terminal.
+    fn finish(&self) {
+        if self.visible {
+            eprint!("\r\x1b[2K");
+        }
+    }
+}
+
+/// Inclusive range of days of a chunk together with its pages, or the error
+/// that occurred while fetching them.
+type ChunkPages = ((NaiveDate, NaiveDate), anyhow::Result<Vec<Value>>);
+
+/// Fetches the pages of all chunks of [`CHUNK_DAYS`] days concurrently.
+///
+/// The chunks are returned in order, independent of the order in which the
+/// requests complete. Once a chunk failed, no further chunks are fetched, so
+/// chunks that weren't fetched yet are missing.
+fn fetch_chunks(
+    api: &GitLabApi,
+    scope: QueryScope,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    timezone: TimeZone,
+) -> Vec<ChunkPages> {
+    let chunks = chunks(start_date, end_date);
+    let progress = Progress::new(chunks.len());
+    let results = Mutex::new((0..chunks.len()).map(|_| None).collect::<Vec<_>>());
+    let next_chunk = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+
+    let workers = api.jobs.get().min(chunks.len());
+    std::thread::scope(|s| {
+        for _ in 0..workers {
+            s.spawn(|| {
+                // Stop early once a chunk failed, as its error is returned
+                // anyway.
+                while !failed.load(Ordering::Relaxed) {
+                    let i = next_chunk.fetch_add(1, Ordering::Relaxed);
+                    let Some(&chunk) = chunks.get(i) else {
+                        break;
+                    };
+                    let result = fetch_chunk_pages(api, scope, chunk, timezone);
+                    failed.fetch_or(result.is_err(), Ordering::Relaxed);
+                    progress.advance();
+                    results.lock().unwrap_or_else(PoisonError::into_inner)[i] = Some(result);
+                }
+            });
+        }
     });
-    response
+    progress.finish();
+
+    // Chunks that were skipped due to an error are `None`.
+    chunks
+        .into_iter()
+        .zip(results.into_inner().unwrap_or_else(PoisonError::into_inner))
+        .filter_map(|(chunk, result)| Some((chunk, result?)))
+        .collect()
+}
+
+/// Fetches all pages from the API with pagination in mind and returns them as
+/// raw JSON, e.g., to save them to a file.
+///
+/// Long time spans are split into chunks of [`CHUNK_DAYS`] days that are
+/// fetched concurrently. The pages are returned in the order of the chunks,
+/// independent of the order in which the requests complete.
+///
+/// # Parameters
+/// - `api`: Connection to the GitLab instance.
+/// - `scope`: Whose timelogs are fetched.
+/// - `start_date`: Inclusive begin date.
+/// - `end_date`: Inclusive end date.
+/// - `timezone`: Timezone in which the days begin and end.
+pub fn fetch_pages(
+    api: &GitLabApi,
+    scope: QueryScope,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    timezone: TimeZone,
+) -> anyhow::Result<Vec<Value>> {
+    // The error of the first failed chunk is returned.
+    let pages = fetch_chunks(api, scope, start_date, end_date, timezone)
+        .into_iter()
+        .map(|(_, pages)| pages)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(pages.into_iter().flatten().collect())
 }
 
-/// Fetches all results from the API with pagination in mind and returns
-/// an aggregated single result.
+/// Parses raw pages, as returned by [`fetch_pages`], and returns an aggregated
+/// single result.
+///
+/// The data of partial responses is used, and a warning with the errors is
+/// printed.
+pub fn pages_into_result(pages: Vec<Value>) -> anyhow::Result<ResponseData> {
+    pages_into_checked_result(pages).map(|(data, _)| data)
+}
+
+/// Like [`pages_into_result`], but also returns whether any page was a
+/// partial response, i.e., whether timelogs may be missing.
+fn pages_into_checked_result(pages: Vec<Value>) -> anyhow::Result<(ResponseData, bool)> {
+    let mut aggregated: Option<ResponseData> = None;
+    let mut partial = false;
+    for page in pages {
+        let (mut next, errors) = parse_response_page(page)?;
+        if let Some(errors) = errors {
+            print_warning(
+                &format!("GitLab returned incomplete data, timelogs may be missing. {errors}"),
+                0,
+            );
+            partial = true;
+        }
+        if let Some(previous) = aggregated {
+            // Ordering here is not that important, happens later anyway.
+            next.timelogs.nodes.extend(previous.timelogs.nodes);
+        }
+        aggregated = Some(next);
+    }
+    let aggregated = aggregated.context("No response pages found")?;
+    Ok((aggregated, partial))
+}
+
+/// Inclusive range of days of a chunk together with its aggregated result and
+/// whether it is partial, or the error that occurred while fetching it.
+pub type ChunkResult = ((NaiveDate, NaiveDate), anyhow::Result<(ResponseData, bool)>);
+
+/// Fetches all results from the API with pagination in mind and returns an
+/// aggregated result per chunk of [`CHUNK_DAYS`] days. See [`fetch_pages`].
+///
+/// Next to the result of each chunk, it is returned whether GitLab responded
+/// with partial data for any of its pages, so that timelogs may be missing.
+/// Once a chunk failed, it is the last one returned, so that the results of
+/// the chunks fetched until then aren't lost.
 ///
 /// # Parameters
-/// - `username`: The exact GitLab username of the user.
-/// - `host`: Host name of the GitLab instance without `https://`
-/// - `token`: GitLab token to access the GitLab instance. Must have at least
-///   READ access.
+/// - `api`: Connection to the GitLab instance.
+/// - `scope`: Whose timelogs are fetched.
 /// - `start_date`: Inclusive begin date.
 /// - `end_date`: Inclusive end date.
+/// - `timezone`: Timezone in which the days begin and end.
 pub fn fetch_results(
-    username: &str,
-    host: &str,
-    token: &str,
+    api: &GitLabApi,
+    scope: QueryScope,
     start_date: NaiveDate,
     end_date: NaiveDate,
-    filter_group: Option<&str>,
-) -> anyhow::Result<ResponseData> {
-    let base = fetch_response_data(
-        username,
-        host,
-        token,
-        None,
-        start_date,
-        end_date,
-        filter_group,
-    )?;
-
-    let mut aggregated = base;
-    while aggregated.timelogs.pageInfo.hasPreviousPage {
-        let cursor = &aggregated
-            .timelogs
-            .pageInfo
-            .startCursor
-            .expect("Should be valid string at this point");
+    timezone: TimeZone,
+) -> Vec<ChunkResult> {
+    let mut results = Vec::new();
+    for (chunk, pages) in fetch_chunks(api, scope, start_date, end_date, timezone) {
+        let result = pages.and_then(pages_into_checked_result);
+        let failed = result.is_err();
+        results.push((chunk, result));
+        if failed {
+            break;
+        }
+    }
+    results
+}
 
-        let mut next = fetch_response_data(
-            username,
-            host,
-            token,
-            Some(cursor),
-            start_date,
-            end_date,
-            filter_group,
-        )?;
+/// Writes raw pages, as returned by [`fetch_pages`], as JSON array to a file.
+pub fn write_pages_to_file(path: &Path, pages: &[Value]) -> anyhow::Result<()> {
+    let content = serde_json::to_string_pretty(pages)?;
+    std::fs::write(path, content)
+        .with_context(|| format!("Failed to write responses to {}", path.display()))
+}
 
-        // Ordering here is not that important, happens later anyway.
-        next.timelogs.nodes.extend(aggregated.timelogs.nodes);
-        aggregated = next;
+/// Reads raw pages from a file written by [`write_pages_to_file`].
+///
+/// For convenience, the file may also contain a single GraphQL response
+/// instead of an array of responses.
+pub fn read_pages_from_file(path: &Path) -> anyhow::Result<Vec<Value>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read responses from {}", path.display()))?;
+    let value = serde_json::from_str::<Value>(&content)
+        .with_context(|| format!("Failed to parse {} as JSON", path.display()))?;
+    match value {
+        Value::Array(pages) => Ok(pages),
+        page => Ok(vec![page]),
     }
-    Ok(aggregated)
 }
 
 #[cfg(test)]
@@ -214,11 +770,13 @@ mod tests {
     fn http_error_message_mentions_invalid_token_for_unauthorized() {
         let msg = http_error_message(
             StatusCode::UNAUTHORIZED,
-            "https://gitlab.example.com/api/graphql",
+            "https://example.com/gitlab/api/graphql",
+            "https://example.com/gitlab",
         );
 
         assert!(msg.contains("401 Unauthorized"));
         assert!(msg.contains("configured token is valid"));
+        assert!(msg.contains("https://example.com/gitlab/-/user_settings/personal_access_tokens"));
     }
 
     #[test]
@@ -226,9 +784,218 @@ mod tests {
         let msg = http_error_message(
             StatusCode::FORBIDDEN,
             "https://gitlab.example.com/api/graphql",
+            "https://gitlab.example.com",
         );
 
         assert!(msg.contains("403 Forbidden"));
         assert!(msg.contains("read_api"));
     }
+
+    #[test]
+    fn graphql_payload_passes_values_as_variables() {
+        let date = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let username = r#"evil" } \ {"#;
+        let payload = graphql_payload(
+            QueryScope::User(username),
+            Some("cursor\"=="),
+            date,
+            date,
+            TimeZone::Local,
+        );
+
+        assert_eq!(payload["query"], GRAPHQL_QUERY);
+        assert_eq!(payload["operationName"], "timelogs");
+        assert_eq!(payload["variables"]["username"], username);
+        assert_eq!(payload["variables"]["before"], "cursor\"==");
+        assert_eq!(payload["variables"]["pageSize"], PAGE_SIZE);
+        assert!(
+            payload["variables"]["startDate"]
+                .as_str()
+                .unwrap()
+                .starts_with("2024-06-01T00:00:00")
+        );
+
+        let payload = graphql_payload(QueryScope::User("user"), None, date, date, TimeZone::Local);
+        assert_eq!(payload["variables"]["before"], Value::Null);
+
+        let payload = graphql_payload(
+            QueryScope::Project("team/app"),
+            None,
+            date,
+            date,
+            TimeZone::Local,
+        );
+        assert_eq!(payload["operationName"], "projectTimelogs");
+        assert_eq!(payload["variables"]["fullPath"], "team/app");
+        assert_eq!(payload["variables"].get("username"), None);
+
+        let timezone = "America/New_York".parse::<TimeZone>().unwrap();
+        let payload = graphql_payload(QueryScope::User("user"), None, date, date, timezone);
+        assert_eq!(
+            payload["variables"]["startDate"],
+            "2024-06-01T00:00:00-04:00"
+        );
+    }
+
+    #[test]
+    fn requested_delay_honours_retry_after_and_rate_limits() {
+        let now = DateTime::parse_from_rfc3339("2024-06-03T12:00:00Z")
+            .unwrap()
+            .to_utc();
+        let headers = |pairs: &[(&'static str, &str)]| {
+            pairs
+                .iter()
+                .map(|&(name, value)| (name.parse().unwrap(), value.parse().unwrap()))
+                .collect::<HeaderMap>()
+        };
+        let reset = (now.timestamp() + 20).to_string();
+
+        assert_eq!(requested_delay(&headers(&[]), now), None);
+        assert_eq!(
+            requested_delay(&headers(&[("retry-after", "7")]), now),
+            Some(Duration::from_secs(7))
+        );
+        assert_eq!(
+            requested_delay(
+                &headers(&[("retry-after", "Mon, 03 Jun 2024 12:01:00 GMT")]),
+                now
+            ),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            requested_delay(
+                &headers(&[("ratelimit-remaining", "0"), ("ratelimit-reset", &reset)]),
+                now
+            ),
+            Some(Duration::from_secs(20))
+        );
+        assert_eq!(
+            requested_delay(
+                &headers(&[("ratelimit-remaining", "3"), ("ratelimit-reset", &reset)]),
+                now
+            ),
+            None
+        );
+        assert_eq!(
+            requested_delay(&headers(&[("retry-after", "86400")]), now),
+            Some(MAX_REQUESTED_DELAY)
+        );
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_a_limit() {
+        let delays = (1..=7).map(backoff).collect::<Vec<_>>();
+        assert_eq!(delays, [1, 2, 4, 8, 16, 30, 30].map(Duration::from_secs));
+        assert_eq!(backoff(u32::MAX), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn chunks_cover_the_time_span() {
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+
+        assert_eq!(chunks(date(6, 3), date(6, 9)), [(date(6, 3), date(6, 9))]);
+        assert_eq!(
+            chunks(date(1, 1), date(3, 31)),
+            [
+                (date(1, 1), date(1, 31)),
+                (date(2, 1), date(3, 2)),
+                (date(3, 3), date(3, 31)),
+            ]
+        );
+        assert!(chunks(date(6, 9), date(6, 3)).is_empty());
+    }
+
+    fn page(title: &str, has_previous_page: bool) -> Value {
+        json!({
+            "data": {
+                "timelogs": {
+                    "nodes": [{
+                        "spentAt": "2024-06-03T12:00:00Z",
+                        "timeSpent": 3600,
+                        "summary": null,
+                        "issue": { "title": title, "webUrl": "https://x", "epic": null },
+                        "mergeRequest": null,
+                        "project": { "group": null },
+                    }],
+                    "pageInfo": {
+                        "hasPreviousPage": has_previous_page,
+                        "startCursor": "cursor",
+                    },
+                },
+            },
+        })
+    }
+
+    #[test]
+    fn pages_into_result_aggregates_all_pages() {
+        let response = pages_into_result(vec![page("A", true), page("B", false)]).unwrap();
+        let titles = response
+            .timelogs
+            .nodes
+            .iter()
+            .map(|node| node.target.title())
+            .collect::<Vec<_>>();
+
+        assert_eq!(titles, ["B", "A"]);
+        assert!(!response.timelogs.pageInfo.hasPreviousPage);
+        assert!(pages_into_result(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn pages_into_result_reports_graphql_errors() {
+        let error = json!({ "errors": [{ "message": "boom", "locations": [] }] });
+        let err = pages_into_result(vec![page("A", true), error]).unwrap_err();
+
+        assert!(err.to_string().contains("boom"));
+    }
+
+    #[test]
+    fn pages_into_result_uses_data_of_partial_responses() {
+        let mut partial = page("A", false);
+        partial["errors"] = json!([{ "message": "epic not accessible", "locations": [] }]);
+        let response = pages_into_result(vec![partial]).unwrap();
+        assert_eq!(response.timelogs.nodes.len(), 1);
+
+        let err = pages_into_result(vec![json!({})]).unwrap_err();
+        assert!(err.to_string().contains("without data"));
+    }
+
+    #[test]
+    fn pages_into_result_reports_errors_of_null_timelogs() {
+        let errors = json!([{ "message": "Internal server error", "locations": [] }]);
+        for data in [
+            json!({ "timelogs": null }),
+            json!({ "group": null }),
+            json!({ "project": { "timelogs": null } }),
+        ] {
+            let page = json!({ "data": data, "errors": errors });
+            let err = pages_into_result(vec![page]).unwrap_err();
+            assert!(err.to_string().contains("Internal server error"), "{err}");
+        }
+
+        let err = pages_into_result(vec![json!({ "data": { "project": null } })]).unwrap_err();
+        assert!(
+            err.to_string().contains("The project doesn't exist"),
+            "{err}"
+        );
+        let err = pages_into_result(vec![json!({ "data": { "timelogs": null } })]).unwrap_err();
+        assert!(err.to_string().contains("without data"), "{err}");
+    }
+
+    #[test]
+    fn parse_current_user_handles_anonymous_and_errors() {
+        let user = parse_current_user(json!({
+            "data": { "currentUser": { "username": "jdoe", "name": "Jane Doe" } }
+        }))
+        .unwrap()
+        .unwrap();
+        assert_eq!(user.username, "jdoe");
+        assert_eq!(user.name, "Jane Doe");
+
+        let anonymous = parse_current_user(json!({ "data": { "currentUser": null } })).unwrap();
+        assert_eq!(anonymous, None);
+
+        let error = json!({ "errors": [{ "message": "boom", "locations": [] }] });
+        assert!(parse_current_user(error).is_err());
+    }
 }
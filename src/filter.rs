@@ -0,0 +1,430 @@
+/*
+MIT License
+
+Copyright (c) 2024 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Local filters for the fetched timelogs.
+//!
+//! Filters are applied by [`response_data_apply_filters`] before any
+//! aggregation, so filtered-out time is never part of the views or totals.
+//!
+//! [`response_data_apply_filters`]: crate::fetch::response_data_apply_filters
+
+use crate::gitlab_api::types::ResponseNode;
+use anyhow::{Context, anyhow};
+use regex::{Regex, RegexBuilder};
+use std::fmt::{self, Display};
+use std::num::IntErrorKind;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// The property of a timelog that a filter matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterField {
+    /// Full name or full path of the group of the project.
+    Group,
+    /// Full path of the project.
+    Project,
+    /// Title of the epic of the issue.
+    Epic,
+    /// Title of the issue or merge request.
+    Title,
+    /// Summary of the timelog.
+    Summary,
+    /// Username or name of the user who logged the time.
+    User,
+}
+
+impl FilterField {
+    const ALL: [(&'static str, Self); 6] = [
+        ("group", Self::Group),
+        ("project", Self::Project),
+        ("epic", Self::Epic),
+        ("title", Self::Title),
+        ("summary", Self::Summary),
+        ("user", Self::User),
+    ];
+
+    /// Returns the values of the timelog for this field. Missing values, such
+    /// as the epic of a merge request, never match.
+    fn values(self, node: &ResponseNode) -> Vec<&str> {
+        match self {
+            Self::Group => node
+                .project
+                .group
+                .iter()
+                .flat_map(|group| [group.fullName.as_str(), group.fullPath.as_str()])
+                .collect(),
+            Self::Project => vec![node.project.fullPath.as_str()],
+            Self::Epic => node.epic_name().into_iter().collect(),
+            Self::Title => vec![node.target.title()],
+            Self::Summary => node.summary.as_deref().into_iter().collect(),
+            Self::User => node
+                .user
+                .iter()
+                .flat_map(|user| [user.username.as_str(), user.name.as_str()])
+                .collect(),
+        }
+    }
+}
+
+impl FromStr for FilterField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, field)| *field)
+            .ok_or_else(|| {
+                let names = Self::ALL.map(|(name, _)| name);
+                format!("unknown field `{s}`, expected one of: {}", names.join(", "))
+            })
+    }
+}
+
+/// How the pattern of a filter is interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternKind {
+    /// The value contains the pattern.
+    Substring,
+    /// The value matches the regular expression somewhere.
+    Regex,
+    /// The whole value matches the glob, where `*` matches any sequence of
+    /// characters and `?` any single character.
+    Glob,
+}
+
+/// A single filter as specified on the command line, such as
+/// `group:team-x`, `summary:re:^Meeting`, or `title:glob:*Review*`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterSpec {
+    pub field: FilterField,
+    pub kind: PatternKind,
+    pub pattern: String,
+}
+
+impl FilterSpec {
+    /// Returns the regular expression that is equivalent to the pattern.
+    fn to_regex(&self, ignore_case: bool) -> Result<Regex, regex::Error> {
+        let regex = match self.kind {
+            PatternKind::Substring => regex::escape(&self.pattern),
+            PatternKind::Regex => self.pattern.clone(),
+            PatternKind::Glob => glob_to_regex(&self.pattern),
+        };
+        RegexBuilder::new(&regex)
+            .case_insensitive(ignore_case)
+            .build()
+    }
+}
+
+impl FromStr for FilterSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (field, pattern) = s
+            .split_once(':')
+            .ok_or_else(|| format!("invalid filter `{s}`, expected `<field>:<pattern>`"))?;
+        let field = field.parse()?;
+        let (kind, pattern) = [("re:", PatternKind::Regex), ("glob:", PatternKind::Glob)]
+            .into_iter()
+            .find_map(|(prefix, kind)| pattern.strip_prefix(prefix).map(|pattern| (kind, pattern)))
+            .unwrap_or((PatternKind::Substring, pattern));
+        let spec = Self {
+            field,
+            kind,
+            pattern: pattern.to_string(),
+        };
+        spec.to_regex(false)
+            .map_err(|e| format!("invalid pattern in filter `{s}`: {e}"))?;
+        Ok(spec)
+    }
+}
+
+impl Display for FilterSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, _) = FilterField::ALL
+            .iter()
+            .find(|(_, field)| *field == self.field)
+            .ok_or(fmt::Error)?;
+        let prefix = match self.kind {
+            PatternKind::Substring => "",
+            PatternKind::Regex => "re:",
+            PatternKind::Glob => "glob:",
+        };
+        write!(f, "{name}:{prefix}{}", self.pattern)
+    }
+}
+
+/// Translates a glob into an anchored regular expression.
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+/// Parses a duration such as `90m`, `1h`, or `1h30m`. Plain numbers are
+/// minutes.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid duration `{s}`, expected something like `15m` or `1h30m`");
+    let too_long = || format!("duration `{s}` is too long");
+    if let Ok(minutes) = s.parse::<u64>() {
+        let secs = minutes.checked_mul(60).ok_or_else(too_long)?;
+        return Ok(Duration::from_secs(secs));
+    }
+
+    let mut secs = 0_u64;
+    let mut rest = s;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(invalid)?;
+        let value = rest[..digits].parse::<u64>().map_err(|e| {
+            if *e.kind() == IntErrorKind::PosOverflow {
+                too_long()
+            } else {
+                invalid()
+            }
+        })?;
+        let unit_secs = match rest[digits..].chars().next() {
+            Some('h') => 3600,
+            Some('m') => 60,
+            Some('s') => 1,
+            _ => return Err(invalid()),
+        };
+        secs = value
+            .checked_mul(unit_secs)
+            .and_then(|value_secs| secs.checked_add(value_secs))
+            .ok_or_else(too_long)?;
+        rest = &rest[digits + 1..];
+    }
+    if s.is_empty() {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+#[derive(Debug)]
+struct Matcher {
+    field: FilterField,
+    regex: Regex,
+}
+
+impl Matcher {
+    fn matches(&self, node: &ResponseNode) -> bool {
+        self.field
+            .values(node)
+            .iter()
+            .any(|value| self.regex.is_match(value))
+    }
+}
+
+/// All local filters, ready to be applied on timelogs.
+///
+/// A timelog passes if
+/// - it matches at least one include filter of each field that has include
+///   filters,
+/// - it matches no exclude filter, and
+/// - its duration is within the minimum and maximum duration, if any.
+#[derive(Debug, Default)]
+pub struct Filters {
+    includes: Vec<Matcher>,
+    excludes: Vec<Matcher>,
+    min_duration: Option<Duration>,
+    max_duration: Option<Duration>,
+}
+
+impl Filters {
+    /// Creates the filters. If `ignore_case` is set, all patterns match
+    /// case-insensitively.
+    pub fn new(
+        includes: &[FilterSpec],
+        excludes: &[FilterSpec],
+        ignore_case: bool,
+        min_duration: Option<Duration>,
+        max_duration: Option<Duration>,
+    ) -> anyhow::Result<Self> {
+        let to_matchers = |specs: &[FilterSpec]| {
+            specs
+                .iter()
+                .map(|spec| {
+                    spec.to_regex(ignore_case)
+                        .map(|regex| Matcher {
+                            field: spec.field,
+                            regex,
+                        })
+                        .with_context(|| format!("invalid filter `{spec}`"))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()
+        };
+
+        if let (Some(min), Some(max)) = (min_duration, max_duration) {
+            if min > max {
+                return Err(anyhow!(
+                    "the minimum duration must not be greater than the maximum duration"
+                ));
+            }
+        }
+
+        Ok(Self {
+            includes: to_matchers(includes)?,
+            excludes: to_matchers(excludes)?,
+            min_duration,
+            max_duration,
+        })
+    }
+
+    /// Returns whether the timelog passes all filters.
+    pub fn matches(&self, node: &ResponseNode) -> bool {
+        let (_, duration) = node.timeSpent();
+        if self.min_duration.is_some_and(|min| duration < min)
+            || self.max_duration.is_some_and(|max| duration > max)
+        {
+            return false;
+        }
+
+        if self.excludes.iter().any(|matcher| matcher.matches(node)) {
+            return false;
+        }
+
+        FilterField::ALL.iter().all(|(_, field)| {
+            let mut includes = self
+                .includes
+                .iter()
+                .filter(|matcher| matcher.field == *field)
+                .peekable();
+            includes.peek().is_none() || includes.any(|matcher| matcher.matches(node))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gitlab_api::test_utils::node;
+
+    fn specs(specs: &[&str]) -> Vec<FilterSpec> {
+        specs.iter().map(|spec| spec.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn filter_spec_parses_kinds_and_rejects_invalid_input() {
+        let spec = "summary:re:^Meet".parse::<FilterSpec>().unwrap();
+        assert_eq!(spec.field, FilterField::Summary);
+        assert_eq!(spec.kind, PatternKind::Regex);
+        assert_eq!(spec.pattern, "^Meet");
+        assert_eq!(spec.to_string(), "summary:re:^Meet");
+
+        let spec = "group:team:x".parse::<FilterSpec>().unwrap();
+        assert_eq!(spec.kind, PatternKind::Substring);
+        assert_eq!(spec.pattern, "team:x");
+
+        assert!("group".parse::<FilterSpec>().is_err());
+        assert!("color:red".parse::<FilterSpec>().is_err());
+        assert!("title:re:(".parse::<FilterSpec>().is_err());
+    }
+
+    #[test]
+    fn parse_duration_supports_units() {
+        assert_eq!(parse_duration("15"), Ok(Duration::from_secs(15 * 60)));
+        assert_eq!(parse_duration("15m"), Ok(Duration::from_secs(15 * 60)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("45s"), Ok(Duration::from_secs(45)));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("1d").is_err());
+        assert!(parse_duration("h").is_err());
+    }
+
+    #[test]
+    fn parse_duration_rejects_overflows() {
+        for s in [
+            "999999999999999999h",
+            "99999999999999999999h",
+            "999999999999999999",
+            "18446744073709551615s1s",
+        ] {
+            let err = parse_duration(s).unwrap_err();
+            assert!(err.contains("too long"), "{err}");
+        }
+    }
+
+    #[test]
+    fn filters_combine_includes_excludes_and_durations() {
+        let mut standup = node("2024-06-03T09:00:00Z", 900, "Daily", None, Some("team-a"));
+        standup.summary = Some("Standup meeting".to_string());
+        let feature = node(
+            "2024-06-03T10:00:00Z",
+            7200,
+            "Feature X",
+            Some("Epic"),
+            Some("team-a"),
+        );
+        let other = node("2024-06-03T12:00:00Z", 3600, "Review", None, Some("team-b"));
+        let nodes = [&standup, &feature, &other];
+
+        let passing = |filters: &Filters| {
+            nodes
+                .iter()
+                .filter(|node| filters.matches(node))
+                .map(|node| node.target.title())
+                .collect::<Vec<_>>()
+        };
+
+        let filters = Filters::new(&specs(&["group:team-a"]), &[], false, None, None).unwrap();
+        assert_eq!(passing(&filters), ["Daily", "Feature X"]);
+
+        // Includes of the same field are alternatives.
+        let filters = Filters::new(
+            &specs(&["group:team-a", "group:glob:*-b"]),
+            &specs(&["summary:re:(?i)standup"]),
+            false,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(passing(&filters), ["Feature X", "Review"]);
+
+        let filters = Filters::new(&specs(&["title:FEATURE"]), &[], true, None, None).unwrap();
+        assert_eq!(passing(&filters), ["Feature X"]);
+
+        let filters = Filters::new(
+            &[],
+            &[],
+            false,
+            Some(Duration::from_secs(1800)),
+            Some(Duration::from_secs(3600)),
+        )
+        .unwrap();
+        assert_eq!(passing(&filters), ["Review"]);
+
+        // Merge requests and issues without epic never match epic filters.
+        let filters = Filters::new(&specs(&["epic:"]), &[], false, None, None).unwrap();
+        assert_eq!(passing(&filters), ["Feature X"]);
+    }
+}
@@ -27,19 +27,20 @@ SOFTWARE.
 
 #[allow(non_snake_case)]
 pub mod types {
-    use chrono::{DateTime, Local, NaiveDate};
+    use crate::calendar::TimeZone;
+    use chrono::{DateTime, FixedOffset, NaiveDate, SecondsFormat};
     use fmt::{Debug, Display};
-    use serde::Deserialize;
+    use serde::{Deserialize, Serialize};
     use std::error::Error;
     use std::fmt;
     use std::time::Duration;
 
-    #[derive(Clone, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
     pub struct Epic {
         pub title: String,
     }
 
-    #[derive(Clone, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
     pub struct Issue {
         pub title: String,
         /// Full http link to issue.
@@ -47,27 +48,143 @@ pub mod types {
         pub epic: Option<Epic>,
     }
 
-    #[derive(Clone, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    pub struct MergeRequest {
+        pub title: String,
+        /// Full http link to merge request.
+        pub webUrl: String,
+    }
+
+    /// The entity on which time was logged.
+    #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    pub enum Target {
+        Issue(Issue),
+        MergeRequest(MergeRequest),
+    }
+
+    impl Target {
+        pub fn title(&self) -> &str {
+            match self {
+                Self::Issue(issue) => &issue.title,
+                Self::MergeRequest(mr) => &mr.title,
+            }
+        }
+
+        /// Full http link to the issue or merge request.
+        pub fn web_url(&self) -> &str {
+            match self {
+                Self::Issue(issue) => &issue.webUrl,
+                Self::MergeRequest(mr) => &mr.webUrl,
+            }
+        }
+
+        /// Returns the epic. Merge requests never have one.
+        pub const fn epic(&self) -> Option<&Epic> {
+            match self {
+                Self::Issue(issue) => issue.epic.as_ref(),
+                Self::MergeRequest(_) => None,
+            }
+        }
+
+        /// Short human-readable name of the kind of target.
+        pub const fn kind(&self) -> &'static str {
+            match self {
+                Self::Issue(_) => "Issue",
+                Self::MergeRequest(_) => "MR",
+            }
+        }
+    }
+
+    #[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
     pub struct Group {
         pub fullName: String,
         pub fullPath: String,
     }
 
-    #[derive(Clone, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
     pub struct Project {
         pub group: Option<Group>,
+        /// Full path of the project, such as `group/project`.
+        #[serde(default)]
+        pub fullPath: String,
     }
 
-    #[derive(Clone, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    #[serde(try_from = "ResponseNodeSerialized", into = "ResponseNodeSerialized")]
     pub struct ResponseNode {
-        pub spentAt: String,
+        pub spentAt: DateTime<FixedOffset>,
         /// For some totally weird reason, GitLab allows negative times.
         /// We recommend just deleting these records. But to support the
         /// deserialization, we have to do it like that.
         pub timeSpent: i64,
         pub summary: Option<String>,
-        pub issue: Issue,
+        pub target: Target,
         pub project: Project,
+        /// The user who logged the time.
+        pub user: Option<User>,
+    }
+
+    /// A timelog as it comes from GitLab, where exactly one of `issue` and
+    /// `mergeRequest` is expected to be set.
+    #[derive(Deserialize, Serialize)]
+    struct ResponseNodeSerialized {
+        spentAt: String,
+        timeSpent: i64,
+        summary: Option<String>,
+        issue: Option<Issue>,
+        mergeRequest: Option<MergeRequest>,
+        project: Project,
+        #[serde(default)]
+        user: Option<User>,
+    }
+
+    impl TryFrom<ResponseNodeSerialized> for ResponseNode {
+        type Error = String;
+
+        fn try_from(node: ResponseNodeSerialized) -> Result<Self, Self::Error> {
+            let spent_at = DateTime::parse_from_rfc3339(&node.spentAt).map_err(|e| {
+                format!(
+                    "timelog has an invalid `spentAt` value `{}`: {e}",
+                    node.spentAt
+                )
+            })?;
+            let target = match (node.issue, node.mergeRequest) {
+                (Some(issue), None) => Target::Issue(issue),
+                (None, Some(mr)) => Target::MergeRequest(mr),
+                (issue, mr) => {
+                    return Err(format!(
+                        "timelog at {} must belong to either an issue or a merge request: issue={issue:?}, mergeRequest={mr:?}",
+                        node.spentAt
+                    ));
+                }
+            };
+            Ok(Self {
+                spentAt: spent_at,
+                timeSpent: node.timeSpent,
+                summary: node.summary,
+                target,
+                project: node.project,
+                user: node.user,
+            })
+        }
+    }
+
+    impl From<ResponseNode> for ResponseNodeSerialized {
+        fn from(node: ResponseNode) -> Self {
+            let (issue, mergeRequest) = match node.target {
+                Target::Issue(issue) => (Some(issue), None),
+                Target::MergeRequest(mr) => (None, Some(mr)),
+            };
+            Self {
+                spentAt: node.spentAt.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+                timeSpent: node.timeSpent,
+                summary: node.summary,
+                issue,
+                mergeRequest,
+                project: node.project,
+                user: node.user,
+            }
+        }
     }
 
     impl ResponseNode {
@@ -78,17 +195,15 @@ pub mod types {
         }
 
         pub fn epic_name(&self) -> Option<&str> {
-            self.issue.epic.as_ref().map(|e| e.title.as_str())
+            self.target.epic().map(|e| e.title.as_str())
         }
 
-        /// Parses the UTC timestring coming from GitLab in the local timezone of
-        /// the user. This is necessary so that entries accounted to a Monday on
+        /// Returns the day of the UTC timestamp coming from GitLab in the given
+        /// timezone. This is necessary so that entries accounted to a Monday on
         /// `00:00` in CEST are not displayed as Sunday. The value is returned
-        /// as [`NaiveDate`] but adjusted to the local time.
-        pub fn datetime(&self) -> NaiveDate {
-            let date = DateTime::parse_from_rfc3339(&self.spentAt).unwrap();
-            let datetime = DateTime::<Local>::from(date);
-            datetime.naive_local().date()
+        /// as [`NaiveDate`] but adjusted to the timezone.
+        pub fn datetime(&self, timezone: TimeZone) -> NaiveDate {
+            timezone.date_of(&self.spentAt)
         }
     }
 
@@ -104,11 +219,81 @@ pub mod types {
         pub pageInfo: ResponsePageInfo,
     }
 
-    #[derive(Clone, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
     pub struct ResponseData {
         pub timelogs: ResponseTimelogs,
     }
 
+    /// A group or project with its timelogs.
+    #[derive(Clone, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    pub struct ResponseNamespace {
+        timelogs: Option<ResponseTimelogs>,
+    }
+
+    /// Deserializes a present field as `Some`, even if it is `null`, so that
+    /// `Option<Option<T>>` distinguishes a missing field from a `null` one.
+    fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        T: Deserialize<'de>,
+        D: serde::Deserializer<'de>,
+    {
+        T::deserialize(deserializer).map(Some)
+    }
+
+    /// The data of the timelogs queries, where the timelogs are either
+    /// top-level or below the queried group or project. Each of them is
+    /// `null` if GitLab couldn't resolve it.
+    #[derive(Clone, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    pub struct ResponseDataSerialized {
+        timelogs: Option<ResponseTimelogs>,
+        #[serde(default, deserialize_with = "deserialize_some")]
+        group: Option<Option<ResponseNamespace>>,
+        #[serde(default, deserialize_with = "deserialize_some")]
+        project: Option<Option<ResponseNamespace>>,
+    }
+
+    impl ResponseDataSerialized {
+        /// Returns the timelogs of the queried user, group, or project.
+        ///
+        /// If they are `null`, the kind of the queried namespace is returned
+        /// as error: `group`, `project`, or `None` for users.
+        fn into_data(self) -> Result<ResponseData, Option<&'static str>> {
+            let (kind, timelogs) = match (self.group, self.project) {
+                (Some(group), _) => (Some("group"), group.and_then(|group| group.timelogs)),
+                (_, Some(project)) => (
+                    Some("project"),
+                    project.and_then(|project| project.timelogs),
+                ),
+                (None, None) => (None, self.timelogs),
+            };
+            timelogs
+                .map(|timelogs| ResponseData { timelogs })
+                .ok_or(kind)
+        }
+    }
+
+    /// A GitLab user, e.g., the owner of the token or the author of a
+    /// timelog.
+    #[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    pub struct User {
+        pub username: String,
+        /// Display name of the user.
+        pub name: String,
+    }
+
+    #[derive(Clone, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    pub struct CurrentUserData {
+        /// `None` if the request is not authenticated.
+        pub currentUser: Option<User>,
+    }
+
+    /// The serialized GraphQL response of the `currentUser` query.
+    #[derive(Clone, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+    pub struct CurrentUserResponseSerialized {
+        pub data: Option<CurrentUserData>,
+        pub errors: Option<GraphQLErrorsResponse>,
+    }
+
     #[derive(Clone, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
     pub struct GraphQLErrorLocation {
         line: u64,
@@ -165,45 +350,181 @@ pub mod types {
     /// timelogs for the given time frame.
     #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
     pub enum Response {
-        PayloadResponse(ResponseData),
-        ErrorResponse(GraphQLErrorsResponse),
-    }
-
-    impl Response {
-        /// Transforms the GraphQL response to a Rust [`Result`].
-        pub fn into_result(self) -> Result<ResponseData, GraphQLErrorsResponse> {
-            match self {
-                Self::PayloadResponse(payload) => Ok(payload),
-                Self::ErrorResponse(errors) => Err(errors),
-            }
-        }
+        Data(ResponseData),
+        /// Data together with errors, e.g., if some fields couldn't be
+        /// resolved. The data may be incomplete.
+        Partial(ResponseData, GraphQLErrorsResponse),
+        Errors(GraphQLErrorsResponse),
+        /// The queried `group` or `project` is `null` without any errors,
+        /// i.e., it doesn't exist or is not accessible with the token.
+        Inaccessible(&'static str),
+        /// Neither data nor errors.
+        Empty,
     }
 
     /// The serialized/typed GraphQL response from the GitLab API with all
     /// timelogs for the given time frame.
     #[derive(Clone, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
     pub struct ResponseSerialized {
-        pub data: Option<ResponseData>,
+        pub data: Option<ResponseDataSerialized>,
         pub errors: Option<GraphQLErrorsResponse>,
     }
 
     impl ResponseSerialized {
-        /// Transforms the GraphQL response to a Rust [`Result`].
+        /// Transforms the GraphQL response into the typed [`Response`].
+        ///
+        /// The errors take precedence over `null` timelogs, as they explain
+        /// why GitLab couldn't resolve them.
         pub fn into_typed(self) -> Response {
-            match self {
-                Self {
-                    data: Some(data),
-                    errors: None,
-                } => Response::PayloadResponse(data),
-                Self {
-                    data: None,
-                    errors: Some(errors),
-                } => Response::ErrorResponse(errors),
-                _ => panic!(
-                    "Unexpected response: data={:#?}, errors={:#?}",
-                    self.data, self.errors
-                ),
+            let data = self
+                .data
+                .map_or(Err(None), ResponseDataSerialized::into_data);
+            match (data, self.errors) {
+                (Ok(data), None) => Response::Data(data),
+                (Ok(data), Some(errors)) => Response::Partial(data, errors),
+                (Err(_), Some(errors)) => Response::Errors(errors),
+                (Err(Some(kind)), None) => Response::Inaccessible(kind),
+                (Err(None), None) => Response::Empty,
             }
         }
     }
 }
+
+/// Helpers to construct API types in unit tests.
+#[cfg(test)]
+pub mod test_utils {
+    use super::types::{Epic, Group, Issue, MergeRequest, Project, ResponseNode, Target};
+    use chrono::DateTime;
+
+    /// Creates a [`ResponseNode`] for an issue with the given properties.
+    ///
+    /// The web URL of the issue is derived from its title.
+    pub fn node(
+        spent_at: &str,
+        time_spent_secs: i64,
+        issue_title: &str,
+        epic_title: Option<&str>,
+        group_path: Option<&str>,
+    ) -> ResponseNode {
+        ResponseNode {
+            spentAt: DateTime::parse_from_rfc3339(spent_at).unwrap(),
+            timeSpent: time_spent_secs,
+            summary: None,
+            target: Target::Issue(Issue {
+                title: issue_title.to_string(),
+                webUrl: format!(
+                    "https://gitlab.example.com/-/issues/{}",
+                    issue_title.replace(' ', "-")
+                ),
+                epic: epic_title.map(|title| Epic {
+                    title: title.to_string(),
+                }),
+            }),
+            project: Project {
+                group: group_path.map(|path| Group {
+                    fullName: path.to_string(),
+                    fullPath: path.to_string(),
+                }),
+                fullPath: group_path
+                    .map_or_else(|| "project".to_string(), |path| format!("{path}/project")),
+            },
+            user: None,
+        }
+    }
+
+    /// Creates a [`ResponseNode`] for a merge request with the given
+    /// properties.
+    pub fn mr_node(spent_at: &str, time_spent_secs: i64, mr_title: &str) -> ResponseNode {
+        ResponseNode {
+            target: Target::MergeRequest(MergeRequest {
+                title: mr_title.to_string(),
+                webUrl: format!(
+                    "https://gitlab.example.com/-/merge_requests/{}",
+                    mr_title.replace(' ', "-")
+                ),
+            }),
+            ..node(spent_at, time_spent_secs, mr_title, None, None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::types::{Response, ResponseNode, ResponseSerialized, Target};
+    use serde_json::json;
+
+    fn node_json(issue: serde_json::Value, merge_request: serde_json::Value) -> serde_json::Value {
+        json!({
+            "spentAt": "2024-06-03T12:00:00Z",
+            "timeSpent": 3600,
+            "summary": null,
+            "issue": issue,
+            "mergeRequest": merge_request,
+            "project": { "group": null },
+        })
+    }
+
+    #[test]
+    fn response_node_deserializes_issue_and_merge_request() {
+        let issue = json!({ "title": "Issue", "webUrl": "https://x/-/issues/1", "epic": null });
+        let mr = json!({ "title": "MR", "webUrl": "https://x/-/merge_requests/1" });
+
+        let node =
+            serde_json::from_value::<ResponseNode>(node_json(issue.clone(), json!(null))).unwrap();
+        assert!(matches!(node.target, Target::Issue(_)));
+        assert_eq!(node.target.title(), "Issue");
+
+        let node =
+            serde_json::from_value::<ResponseNode>(node_json(json!(null), mr.clone())).unwrap();
+        assert!(matches!(node.target, Target::MergeRequest(_)));
+        assert_eq!(node.target.web_url(), "https://x/-/merge_requests/1");
+        assert_eq!(node.epic_name(), None);
+
+        assert!(
+            serde_json::from_value::<ResponseNode>(node_json(json!(null), json!(null))).is_err()
+        );
+        assert!(serde_json::from_value::<ResponseNode>(node_json(issue.clone(), mr)).is_err());
+
+        let mut invalid = node_json(issue, json!(null));
+        invalid["spentAt"] = json!("yesterday");
+        let err = serde_json::from_value::<ResponseNode>(invalid).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("invalid `spentAt` value `yesterday`")
+        );
+
+        let json = serde_json::to_value(&node).unwrap();
+        assert_eq!(json["spentAt"], "2024-06-03T12:00:00Z");
+    }
+
+    #[test]
+    fn response_data_accepts_timelogs_of_groups_and_projects() {
+        let issue = json!({ "title": "Issue", "webUrl": "https://x/-/issues/1", "epic": null });
+        let mut node = node_json(issue, json!(null));
+        node["user"] = json!({ "username": "jdoe", "name": "Jane Doe" });
+        let timelogs = json!({
+            "nodes": [node],
+            "pageInfo": { "hasPreviousPage": false, "startCursor": null },
+        });
+
+        for key in ["timelogs", "group", "project"] {
+            let data = if key == "timelogs" {
+                json!({ key: timelogs })
+            } else {
+                json!({ key: { "timelogs": timelogs } })
+            };
+            let response = json!({ "data": data });
+            let response = serde_json::from_value::<ResponseSerialized>(response).unwrap();
+            let Response::Data(data) = response.into_typed() else {
+                panic!("expected data for `{key}`");
+            };
+            let user = data.timelogs.nodes[0].user.as_ref().unwrap();
+            assert_eq!(user.username, "jdoe");
+        }
+
+        // GitLab returns `null` for unknown or inaccessible groups.
+        let response = json!({ "data": { "group": null } });
+        let response = serde_json::from_value::<ResponseSerialized>(response).unwrap();
+        assert_eq!(response.into_typed(), Response::Inaccessible("group"));
+    }
+}
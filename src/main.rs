@@ -41,241 +41,221 @@ SOFTWARE.
 #![deny(missing_debug_implementations)]
 #![deny(rustdoc::all)]
 
+use crate::cache::{CacheMode, fetch_results_cached};
 use crate::cfg::get_cfg;
-use crate::cli::CliArgs;
-use crate::fetch::fetch_results;
-use crate::gitlab_api::types::ResponseNode;
+use crate::cli::{CliArgs, Command};
+use crate::fetch::response_data_apply_filters;
+use crate::gitlab_api::types::ResponseData;
+use crate::output::Report;
 use anyhow::Context;
-use chrono::{Datelike, NaiveDate, Weekday};
 use nu_ansi_term::{Color, Style};
+use reqwest::blocking::Client;
 use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::process::ExitCode;
 use std::time::Duration;
 
+mod absences;
+mod cache;
+mod calendar;
 mod cfg;
 mod cli;
 mod fetch;
+mod filter;
 mod gitlab_api;
+mod output;
+mod rules;
 mod views;
-
-fn main() -> Result<(), Box<dyn Error>> {
-    let cfg = get_cfg()?;
-    cfg.validate().context("Failed to validate config")?;
-
-    let response = fetch_results(
-        cfg.username(),
-        cfg.host(),
-        cfg.token(),
-        cfg.after(),
-        cfg.before(),
-        cfg.filter_group(),
-    )?;
-
-    println!("Host     : {}", cfg.host());
-    println!("Username : {}", cfg.username());
-    println!("Time Span: {} - {}", cfg.after(), cfg.before());
+mod working_hours;
+
+/// Exit code of the `check` subcommand if it found violations.
+const EXIT_CODE_CHECK_FAILED: u8 = 2;
+
+fn main() -> Result<ExitCode, Box<dyn Error>> {
+    // One config per profile and user; the output options are the same for
+    // all.
+    // A single HTTP client for all requests, so that connections are reused.
+    let client = Client::new();
+    let mut cfgs = Vec::new();
+    for mut cfg in get_cfg()? {
+        cfg.validate().context("Failed to validate config")?;
+        let mut result = resolve_current_user(&mut cfg, &client);
+        if let Some(profile) = cfg.profile() {
+            result = result.with_context(|| format!("Failed to load profile `{profile}`"));
+        }
+        result?;
+        cfgs.extend(cfg.split_by_user());
+    }
+    let is_combined = cfgs.len() > 1 || cfgs[0].all_profiles();
+
+    let mut responses = Vec::with_capacity(cfgs.len());
+    for cfg in &cfgs {
+        let mut response = load_response(cfg, &client);
+        if is_combined || cfg.profile().is_some() {
+            response = response.with_context(|| {
+                format!(
+                    "Failed to load timelogs of {}",
+                    [cfg.profile(), cfg.username()]
+                        .into_iter()
+                        .flatten()
+                        .collect::<Vec<_>>()
+                        .join("/")
+                )
+            });
+        }
+        let response = response?;
+        responses.push(response_data_apply_filters(response, &cfg.filters()?));
+    }
 
     // All nodes but as vector to references.
     // Simplifies the handling with other parts of the code, especially the
     // `views` module.
-    let nodes = response.timelogs.nodes.iter().collect::<Vec<_>>();
+    let cfg = &cfgs[0];
+    let nodes = responses
+        .iter()
+        .map(|response| response.timelogs.nodes.iter().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let reports = cfgs
+        .iter()
+        .zip(&nodes)
+        .map(|(cfg, nodes)| Report::new(nodes, cfg))
+        .collect::<Vec<_>>();
+
+    // Colors only make sense for the terminal.
+    let colored = cfg.output_file().is_none();
+    let mut out = open_output(cfg)?;
+
+    if let Some(Command::Check { fail_on }) = cfg.command() {
+        let severity = output::check::write_findings(&mut out, &reports, colored)?;
+        out.flush()?;
+        return Ok(if severity.is_some_and(|severity| severity >= fail_on) {
+            ExitCode::from(EXIT_CODE_CHECK_FAILED)
+        } else {
+            ExitCode::SUCCESS
+        });
+    }
 
-    if nodes.is_empty() {
-        print_warning(
-            "No entries found in this time span.\nUse --after/--before or --month to choose a different range.",
-            0,
-        );
+    let renderer = output::renderer(cfg.output(), colored);
+    if is_combined {
+        renderer.render_combined(&mut out, &reports)?;
     } else {
-        print_all_weeks(nodes.as_slice(), &cfg);
+        renderer.render(&mut out, &reports[0])?;
     }
+    out.flush()?;
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
 
-fn print_timelog(log: &ResponseNode) {
-    let (duration_is_positive, duration) = log.timeSpent();
-    print!("  ");
-    print_duration(duration, Color::Magenta);
-    println!(
-        "  {issue_name}",
-        issue_name = Style::new()
-            .bold()
-            .fg(Color::Green)
-            .paint(log.issue.title.clone()),
-    );
-    let min_minutes_threshold = 15;
-    if !duration_is_positive {
-        print_warning(
-            "^ ERROR: You have logged this time as NEGATIVE: Update the ticket!",
-            3,
-        );
-    }
-    if duration.as_secs() / 60 < min_minutes_threshold {
-        print_warning("^ WARN: Less than 15 minutes! Is this correct?", 6);
-    }
-
-    // Print issue metadata.
-    let epic_name = log.epic_name().unwrap_or("<no epic>");
-    let whitespace = " ".repeat(11);
-    println!(
-        "{whitespace}{link}",
-        link = Style::new().dimmed().paint(&log.issue.webUrl)
-    );
-    if let Some(group) = &log.project.group {
-        println!(
-            "{whitespace}[{epic_key} {epic_name}, {group_key} {group_name}]",
-            epic_key = Style::new().dimmed().paint("Epic:"),
-            epic_name = Style::new().bold().paint(epic_name),
-            group_key = Style::new().dimmed().paint("Group:"),
-            group_name = Style::new().bold().paint(&group.fullName),
-            whitespace = " ".repeat(11),
-        );
+/// Looks up the owner of the token, unless no network requests are allowed.
+///
+/// The owner is used if no username is configured. A configured username that
+/// differs from the owner is most likely a mistake, so a warning is printed.
+/// If the owner can't be fetched, a configured username is used as it is.
+/// Teams, i.e., multiple usernames, are taken as they are. Groups and projects
+/// don't need a user at all.
+fn resolve_current_user(cfg: &mut CliArgs, client: &Client) -> anyhow::Result<()> {
+    if cfg.usernames().len() > 1 || cfg.is_namespace_scope() {
+        return Ok(());
     }
 
-    if let Some(lines) = log.summary.as_ref().map(|t| t.lines()) {
-        for line in lines {
-            println!("             {line}");
-        }
-    }
-}
-
-fn print_warning(msg: &str, indention: usize) {
-    println!(
-        "{indention}{msg}",
-        indention = " ".repeat(indention),
-        msg = Style::new().bold().fg(Color::Yellow).paint(msg),
-    );
-}
-
-fn print_date(day: &NaiveDate, nodes_of_day: &[&ResponseNode]) {
-    let total = views::to_time_spent_sum(nodes_of_day);
-
-    let day_print = format!("{day}, {}", day.weekday());
-
-    print!("{}  (", Style::new().bold().paint(day_print));
-    print_duration(total, Color::Blue);
-    println!(")");
-
-    // Sanity checks and print warnings
-    {
-        let max_hours_threshold = 10;
-        if total.as_secs() > max_hours_threshold * 60 * 60 {
-            print_warning("^ WARN: More than 10 hours! Is this correct?", 18);
-        }
-
-        match day.weekday() {
-            Weekday::Sat | Weekday::Sun => {
-                print_warning("^ WARN: You shouldn't work on the weekend, right?", 12);
+    let is_online = cfg.replay_file().is_none() && cfg.cache_mode() != CacheMode::Offline;
+    if let (true, Some(api)) = (is_online, cfg.api(client)) {
+        // The owner is only required if no username is configured; otherwise,
+        // it is only used for the display name and the check above.
+        let user = match fetch::fetch_current_user(&api) {
+            Ok(user) => user,
+            Err(e) if cfg.username().is_some() => {
+                print_warning(
+                    &format!("WARN: Failed to fetch the owner of the token: {e:#}"),
+                    0,
+                );
+                None
+            }
+            Err(e) => return Err(e.context("Failed to fetch the owner of the token")),
+        };
+        match (user, cfg.username()) {
+            (Some(user), configured) => {
+                if let Some(configured) = configured.filter(|&c| c != user.username) {
+                    print_warning(
+                        &format!(
+                            "WARN: The configured username `{configured}` differs from `{}`, the owner of the token!",
+                            user.username
+                        ),
+                        0,
+                    );
+                }
+                cfg.set_current_user(user);
             }
-            _ => {}
+            (None, None) => {
+                anyhow::bail!("The token doesn't belong to a user; please specify --username")
+            }
+            (None, Some(_)) => {}
         }
     }
 
-    for log in nodes_of_day {
-        print_timelog(log);
+    if cfg.username().is_none() {
+        anyhow::bail!("--username is required when using --offline or --from-file");
     }
+    Ok(())
 }
 
-fn print_week(week: (i32 /* year */, u32 /* iso week */), nodes_of_week: &[&ResponseNode]) {
-    let week_style = Style::new().bold();
-    let week_print = format!("WEEK {}-W{:02}", week.0, week.1);
-    println!(
-        "{delim} {week_print} {delim}",
-        delim = week_style.paint("======================"),
-        week_print = week_style.paint(week_print)
-    );
-    let total_week_time = views::to_time_spent_sum(nodes_of_week);
-    print!(
-        "{total_time_key}       ",
-        total_time_key = Style::new().bold().paint("Total time:")
-    );
-    print_duration(total_week_time, Color::Blue);
-    println!();
-    println!();
-
-    let nodes_by_day = views::to_nodes_by_day(nodes_of_week);
-
-    for (i, (day, nodes)) in nodes_by_day.iter().enumerate() {
-        print_date(day, nodes);
+/// Loads the timelogs either from a file with saved responses, or from GitLab
+/// and the cache.
+fn load_response(cfg: &CliArgs, client: &Client) -> anyhow::Result<ResponseData> {
+    let scope = cfg.scope().context("The username is unknown")?;
 
-        let is_last = i == nodes_by_day.len() - 1;
-        if !is_last {
-            println!();
-        }
-    }
-}
-
-fn print_extended_summary(nodes: &[&ResponseNode]) {
-    {
-        let nodes_by_epic = views::to_nodes_by_epic(nodes);
-        for (epic, nodes_of_epic) in nodes_by_epic {
-            let duration = views::to_time_spent_sum(&nodes_of_epic);
-            print!("  ");
-            print_duration(duration, Color::Magenta);
-            print!(
-                " - {epic_key}  {epic_name}",
-                epic_key = Style::new().dimmed().paint("Epic:"),
-                epic_name = Style::new().bold().paint(
-                    epic.as_ref()
-                        .map(|e| e.title.as_str())
-                        .unwrap_or("<No Epic>")
-                )
-            );
-            println!();
-        }
-    }
-    {
-        let nodes_by_issue = views::to_nodes_by_issue(nodes);
-        for (issue, nodes_of_issue) in nodes_by_issue {
-            let duration = views::to_time_spent_sum(&nodes_of_issue);
-            print!("  ");
-            print_duration(duration, Color::Magenta);
-            print!(
-                " - Issue: {issue_name}",
-                issue_name = Style::new().bold().fg(Color::Green).paint(issue.title)
-            );
-            println!();
-        }
+    if let Some(path) = cfg.replay_file() {
+        let pages = fetch::read_pages_from_file(path)?;
+        return fetch::pages_into_result(pages);
     }
-}
 
-fn print_final_summary(nodes: &[&ResponseNode], cfg: &CliArgs) {
-    // Print separator.
-    {
-        println!();
-        // same length as the week separator
-        println!("{}", "-".repeat(59));
-        println!();
+    if let Some(path) = cfg.save_response() {
+        let api = cfg
+            .api(client)
+            .context("A token is required to fetch timelogs")?;
+        let pages = fetch::fetch_pages(
+            &api,
+            scope,
+            cfg.after(),
+            cfg.before(),
+            cfg.calendar().timezone,
+        )?;
+        fetch::write_pages_to_file(path, &pages)?;
+        return fetch::pages_into_result(pages);
     }
 
-    let total_time = views::to_time_spent_sum(nodes);
-    let all_days = views::to_nodes_by_day(nodes);
-
-    print!(
-        "{total_time_key} ({days_amount:>2} days with records): ",
-        total_time_key = Style::new().bold().paint("Total time"),
-        days_amount = all_days.len(),
-    );
-    print_duration(total_time, Color::Blue);
-    println!();
-
-    if cfg.print_extended_summary() {
-        println!();
-        print_extended_summary(nodes);
-    }
+    fetch_results_cached(
+        scope,
+        &cfg.base_url(),
+        cfg.api(client).as_ref(),
+        cfg.after(),
+        cfg.before(),
+        cfg.calendar().timezone,
+        cfg.cache_mode(),
+    )
 }
 
-fn print_all_weeks(nodes: &[&ResponseNode], cfg: &CliArgs) {
-    let view = views::to_nodes_by_week(nodes);
-    for (i, (week, nodes_of_week)) in view.iter().enumerate() {
-        print_week((week.year(), week.week()), nodes_of_week);
-
-        let is_last = i == view.len() - 1;
-        if !is_last {
-            println!();
+/// Opens the destination for the output: either the file specified by
+/// `--output-file` or stdout.
+fn open_output(cfg: &CliArgs) -> anyhow::Result<Box<dyn Write>> {
+    let out: Box<dyn Write> = match cfg.output_file() {
+        Some(path) => {
+            let file = File::create(path)
+                .with_context(|| format!("Failed to create output file {}", path.display()))?;
+            Box::new(BufWriter::new(file))
         }
-    }
+        None => Box::new(std::io::stdout().lock()),
+    };
+    Ok(out)
+}
 
-    print_final_summary(nodes, cfg);
+fn print_warning(msg: &str, indention: usize) {
+    eprintln!(
+        "{indention}{msg}",
+        indention = " ".repeat(indention),
+        msg = Style::new().bold().fg(Color::Yellow).paint(msg),
+    );
 }
 
 const fn duration_to_hhmm(dur: Duration) -> (u64, u64) {
@@ -285,12 +265,6 @@ const fn duration_to_hhmm(dur: Duration) -> (u64, u64) {
     (hours, minutes)
 }
 
-fn print_duration(duration: Duration, color: Color) {
-    let (hours, minutes) = duration_to_hhmm(duration);
-    let print_str = format!("{hours:>2}h {minutes:02}m");
-    print!("{}", Style::new().bold().fg(color).paint(print_str));
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,162 @@
+/*
+MIT License
+
+Copyright (c) 2024 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Compact list of the findings of the sanity-check rules, as printed by the
+//! `check` subcommand.
+
+use super::{Report, section_titles, target_prefix};
+use crate::rules::{Finding, Severity, Subject};
+use chrono::Datelike;
+use nu_ansi_term::{Color, Style};
+use std::io::{self, Write};
+
+/// Writes the findings of all reports, one per line, followed by the number
+/// of findings per severity. For multiple reports, the findings are grouped
+/// by the section title of the report.
+///
+/// Returns the highest severity of all findings, if any.
+pub fn write_findings(
+    out: &mut dyn Write,
+    reports: &[Report],
+    colored: bool,
+) -> io::Result<Option<Severity>> {
+    let findings = reports.iter().map(Report::findings).collect::<Vec<_>>();
+
+    if reports.len() > 1 {
+        for (title, findings) in section_titles(reports).iter().zip(&findings) {
+            writeln!(out, "{title}:")?;
+            for finding in findings {
+                write_finding(out, finding, 2, colored)?;
+            }
+        }
+    } else {
+        for finding in findings.iter().flatten() {
+            write_finding(out, finding, 0, colored)?;
+        }
+    }
+
+    let count = |severity| {
+        findings
+            .iter()
+            .flatten()
+            .filter(|finding| finding.severity == severity)
+            .count()
+    };
+    let (errors, warnings, infos) = (
+        count(Severity::Error),
+        count(Severity::Warn),
+        count(Severity::Info),
+    );
+    if errors + warnings + infos == 0 {
+        writeln!(out, "No findings.")?;
+    } else {
+        writeln!(
+            out,
+            "Findings: {errors} errors, {warnings} warnings, {infos} infos"
+        )?;
+    }
+
+    Ok(findings
+        .iter()
+        .flatten()
+        .map(|finding| finding.severity)
+        .max())
+}
+
+fn write_finding(
+    out: &mut dyn Write,
+    finding: &Finding,
+    indention: usize,
+    colored: bool,
+) -> io::Result<()> {
+    let subject = match finding.subject {
+        Subject::Week(week) => week.to_string(),
+        Subject::Day(day) | Subject::Timelog(day, _) => format!("{day}, {}", day.weekday()),
+    };
+    let message = match finding.subject {
+        Subject::Timelog(_, node) => format!(
+            "{}{}: {}",
+            target_prefix(&node.target),
+            node.target.title(),
+            finding.message
+        ),
+        Subject::Day(_) | Subject::Week(_) => finding.message.clone(),
+    };
+    let color = match finding.severity {
+        Severity::Info => Color::Blue,
+        Severity::Warn => Color::Yellow,
+        Severity::Error => Color::Red,
+    };
+    let style = if colored {
+        Style::new().bold().fg(color)
+    } else {
+        Style::new()
+    };
+    writeln!(
+        out,
+        "{indention}{subject:<15}  {severity}  {rule:<17}  {message}",
+        indention = " ".repeat(indention),
+        severity = style.paint(format!("{:<5}", finding.severity.to_string())),
+        rule = finding.rule.name(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gitlab_api::test_utils::node;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn write_findings_lists_findings_by_date() {
+        let nodes = [
+            node("2024-06-04T12:00:00Z", -3600, "Issue A", None, None),
+            node("2024-06-08T12:00:00Z", 3600, "Issue B", None, None),
+        ];
+        let nodes = nodes.iter().collect::<Vec<_>>();
+        let mut report = Report::with_metadata(
+            &nodes,
+            "gitlab.example.com",
+            "user",
+            NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 6, 9).unwrap(),
+        );
+        report.today = NaiveDate::from_ymd_opt(2024, 6, 6).unwrap();
+
+        let mut out = Vec::new();
+        let severity = write_findings(&mut out, &[report], false).unwrap();
+
+        assert_eq!(severity, Some(Severity::Error));
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+2024-06-03, Mon  WARN   missing_day        No time logged!
+2024-06-04, Tue  ERROR  negative_duration  Issue A: You have logged this time as NEGATIVE: Update the ticket!
+2024-06-05, Wed  WARN   missing_day        No time logged!
+2024-06-08, Sat  WARN   weekend            You shouldn't work on the weekend, right?
+Findings: 1 errors, 3 warnings, 0 infos
+"
+        );
+    }
+}
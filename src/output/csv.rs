@@ -0,0 +1,186 @@
+/*
+MIT License
+
+Copyright (c) 2024 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! CSV output of all fetched timelogs, one row per timelog.
+//!
+//! The format follows RFC 4180, so that it can be imported into spreadsheets
+//! without further ado.
+
+use super::{Renderer, Report};
+use crate::gitlab_api::types::Target;
+use std::borrow::Cow;
+use std::io::Write;
+
+const HEADER: [&str; 10] = [
+    "date",
+    "duration_minutes",
+    "duration_hours",
+    "type",
+    "title",
+    "url",
+    "epic",
+    "group_path",
+    "user",
+    "summary",
+];
+
+/// Quotes a single CSV field, if necessary.
+///
+/// Fields containing the delimiter, quotes, or line breaks are enclosed in
+/// quotes and inner quotes are doubled.
+fn escape_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+fn write_row<'a>(
+    out: &mut dyn Write,
+    fields: impl IntoIterator<Item = &'a str>,
+) -> std::io::Result<()> {
+    let line = fields
+        .into_iter()
+        .map(escape_field)
+        .collect::<Vec<_>>()
+        .join(",");
+    // RFC 4180 mandates CRLF as record delimiter.
+    write!(out, "{line}\r\n")
+}
+
+/// Writes one row per node of the report, each starting with the `leading`
+/// columns.
+fn write_rows(out: &mut dyn Write, report: &Report, leading: &[&str]) -> std::io::Result<()> {
+    for (date, nodes_of_day) in report.days() {
+        for node in nodes_of_day {
+            let date = date.to_string();
+            let minutes = (node.timeSpent / 60).to_string();
+            let hours = format!("{:.2}", node.timeSpent as f64 / 3600.0);
+            let group = node
+                .project
+                .group
+                .as_ref()
+                .map(|g| g.fullPath.as_str())
+                .unwrap_or_default();
+            write_row(
+                out,
+                leading.iter().copied().chain([
+                    date.as_str(),
+                    minutes.as_str(),
+                    hours.as_str(),
+                    match node.target {
+                        Target::Issue(_) => "issue",
+                        Target::MergeRequest(_) => "merge_request",
+                    },
+                    node.target.title(),
+                    node.target.web_url(),
+                    node.epic_name().unwrap_or_default(),
+                    group,
+                    node.user
+                        .as_ref()
+                        .map(|user| user.username.as_str())
+                        .unwrap_or_default(),
+                    node.summary.as_deref().unwrap_or_default(),
+                ]),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Renders all nodes as CSV, one row per node, ordered by day.
+///
+/// Durations are signed, as GitLab allows negative timelogs. Combined
+/// timesheets have the additional leading columns `profile` and `username`.
+#[derive(Debug)]
+pub struct CsvRenderer;
+
+impl Renderer for CsvRenderer {
+    fn render(&self, out: &mut dyn Write, report: &Report) -> anyhow::Result<()> {
+        write_row(out, HEADER)?;
+        write_rows(out, report, &[])?;
+        Ok(())
+    }
+
+    fn render_combined(&self, out: &mut dyn Write, reports: &[Report]) -> anyhow::Result<()> {
+        write_row(out, ["profile", "username"].into_iter().chain(HEADER))?;
+        for report in reports {
+            let profile = report.profile.unwrap_or_default();
+            write_rows(out, report, &[profile, report.username])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gitlab_api::test_utils::node;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn escape_field_quotes_special_characters() {
+        assert_eq!(escape_field("plain"), "plain");
+        assert_eq!(escape_field("a, b"), "\"a, b\"");
+        assert_eq!(escape_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_field("line1\nline2"), "\"line1\nline2\"");
+    }
+
+    #[test]
+    fn write_csv_writes_one_row_per_node() {
+        let mut second = node(
+            "2024-06-04T12:00:00Z",
+            -900,
+            "Fix foo, bar",
+            None,
+            Some("team/project"),
+        );
+        second.summary = Some("first\nsecond".to_string());
+        let nodes = [
+            second,
+            node("2024-06-03T12:00:00Z", 5400, "Issue", Some("Epic"), None),
+        ];
+        let nodes = nodes.iter().collect::<Vec<_>>();
+
+        let report = Report::with_metadata(
+            &nodes,
+            "gitlab.example.com",
+            "user",
+            NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 6, 9).unwrap(),
+        );
+
+        let mut out = Vec::new();
+        CsvRenderer.render(&mut out, &report).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+
+        assert_eq!(
+            csv,
+            "date,duration_minutes,duration_hours,type,title,url,epic,group_path,user,summary\r\n\
+             2024-06-03,90,1.50,issue,Issue,https://gitlab.example.com/-/issues/Issue,Epic,,,\r\n\
+             2024-06-04,-15,-0.25,issue,\"Fix foo, bar\",\"https://gitlab.example.com/-/issues/Fix-foo,-bar\",,team/project,,\"first\nsecond\"\r\n"
+        );
+    }
+}
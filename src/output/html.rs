@@ -0,0 +1,378 @@
+/*
+MIT License
+
+Copyright (c) 2024 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Self-contained HTML timesheet report.
+
+use super::{Renderer, Report, format_duration, target_prefix, week_total};
+use crate::gitlab_api::types::ResponseNode;
+use crate::views;
+use chrono::Datelike;
+use std::io::Write;
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.25em 0.5em; text-align: left; vertical-align: top; }
+td.duration { text-align: right; white-space: nowrap; }
+td.summary { white-space: pre-wrap; }
+td.missing { background: #fdd; font-weight: bold; }";
+
+/// Escapes text for the use in HTML content and attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Writes a table of the timelogs. With `show_user`, the table has an
+/// additional column for the user who logged the time.
+fn write_timelog_table(
+    out: &mut dyn Write,
+    nodes: &[&ResponseNode],
+    show_user: bool,
+) -> std::io::Result<()> {
+    writeln!(out, "<table>")?;
+    writeln!(
+        out,
+        "<tr><th>Duration</th><th>Issue / MR</th><th>Epic</th><th>Group</th>{}<th>Summary</th></tr>",
+        if show_user { "<th>User</th>" } else { "" }
+    )?;
+    for node in nodes {
+        let (is_positive, duration) = node.timeSpent();
+        let sign = if is_positive { "" } else { "-" };
+        writeln!(
+            out,
+            "<tr><td class=\"duration\">{sign}{duration}</td><td>{prefix}<a href=\"{url}\">{title}</a></td><td>{epic}</td><td>{group}</td>{user}<td class=\"summary\">{summary}</td></tr>",
+            duration = format_duration(duration),
+            prefix = target_prefix(&node.target),
+            url = escape(node.target.web_url()),
+            title = escape(node.target.title()),
+            epic = escape(node.epic_name().unwrap_or_default()),
+            group = escape(
+                node.project
+                    .group
+                    .as_ref()
+                    .map(|g| g.fullPath.as_str())
+                    .unwrap_or_default()
+            ),
+            user = if show_user {
+                format!("<td>{}</td>", escape(&super::timelog_user(node)))
+            } else {
+                String::new()
+            },
+            summary = escape(node.summary.as_deref().unwrap_or_default()),
+        )?;
+    }
+    writeln!(out, "</table>")
+}
+
+fn write_extended_summary(
+    out: &mut dyn Write,
+    report: &Report,
+    level: usize,
+) -> std::io::Result<()> {
+    writeln!(out, "<h{level}>Summary per Epic</h{level}>")?;
+    writeln!(out, "<table>")?;
+    writeln!(out, "<tr><th>Duration</th><th>Epic</th></tr>")?;
+    for (epic, nodes_of_epic) in &report.epics {
+        writeln!(
+            out,
+            "<tr><td class=\"duration\">{duration}</td><td>{epic}</td></tr>",
+            duration = format_duration(views::to_time_spent_sum(nodes_of_epic)),
+            epic = escape(epic.as_ref().map_or("<No Epic>", |e| e.title.as_str())),
+        )?;
+    }
+    writeln!(out, "</table>")?;
+
+    writeln!(
+        out,
+        "<h{level}>Summary per Issue and Merge Request</h{level}>"
+    )?;
+    writeln!(out, "<table>")?;
+    writeln!(out, "<tr><th>Duration</th><th>Issue / MR</th></tr>")?;
+    for (target, nodes_of_target) in &report.targets {
+        writeln!(
+            out,
+            "<tr><td class=\"duration\">{duration}</td><td>{prefix}<a href=\"{url}\">{title}</a></td></tr>",
+            duration = format_duration(views::to_time_spent_sum(nodes_of_target)),
+            prefix = target_prefix(target),
+            url = escape(target.web_url()),
+            title = escape(target.title()),
+        )?;
+    }
+    writeln!(out, "</table>")
+}
+
+/// Writes the total per section and the matrix of sections and days of a
+/// combined timesheet. Working days without any time are marked as missing.
+fn write_overview(
+    out: &mut dyn Write,
+    reports: &[Report],
+    titles: &[String],
+) -> std::io::Result<()> {
+    writeln!(out, "<table>")?;
+    writeln!(
+        out,
+        "<tr><th>Name</th><th>Total</th><th>Days with records</th></tr>"
+    )?;
+    for (report, title) in reports.iter().zip(titles) {
+        writeln!(
+            out,
+            "<tr><td>{}</td><td class=\"duration\">{}</td><td class=\"duration\">{}</td></tr>",
+            escape(title),
+            format_duration(report.total_time()),
+            report.days_with_records()
+        )?;
+    }
+    writeln!(out, "</table>")?;
+
+    let days = super::span_days(reports);
+    writeln!(out, "<table>")?;
+    write!(out, "<tr><th>Name</th>")?;
+    for day in &days {
+        write!(out, "<th>{} {}</th>", day.format("%m-%d"), day.weekday())?;
+    }
+    writeln!(out, "<th>Total</th></tr>")?;
+
+    for (report, title) in reports.iter().zip(titles) {
+        write!(out, "<tr><td>{}</td>", escape(title))?;
+        for day in &days {
+            let total = report.day_total(*day);
+            if report.is_missing_day(*day) {
+                write!(out, "<td class=\"missing\">missing</td>")?;
+            } else if total.is_zero() {
+                write!(out, "<td class=\"duration\">-</td>")?;
+            } else {
+                write!(
+                    out,
+                    "<td class=\"duration\">{}</td>",
+                    format_duration(total)
+                )?;
+            }
+        }
+        writeln!(
+            out,
+            "<td class=\"duration\">{}</td></tr>",
+            format_duration(report.total_time())
+        )?;
+    }
+
+    write!(out, "<tr><th>Total</th>")?;
+    for day in &days {
+        let total = reports.iter().map(|report| report.day_total(*day)).sum();
+        write!(
+            out,
+            "<td class=\"duration\">{}</td>",
+            format_duration(total)
+        )?;
+    }
+    writeln!(
+        out,
+        "<td class=\"duration\">{}</td></tr>",
+        format_duration(super::grand_total(reports))
+    )?;
+    writeln!(out, "</table>")
+}
+
+fn write_page_head(out: &mut dyn Write, title: &str) -> std::io::Result<()> {
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html>")?;
+    writeln!(out, "<head>")?;
+    writeln!(out, "<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{}</title>", escape(title))?;
+    writeln!(out, "<style>\n{STYLE}\n</style>")?;
+    writeln!(out, "</head>")?;
+    writeln!(out, "<body>")
+}
+
+fn write_page_tail(out: &mut dyn Write) -> std::io::Result<()> {
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")
+}
+
+/// Writes the report with `title` as heading of the given level. The
+/// sections of the report use the levels below.
+fn write_report(
+    out: &mut dyn Write,
+    report: &Report,
+    title: &str,
+    level: usize,
+) -> std::io::Result<()> {
+    writeln!(out, "<h{level}>{}</h{level}>", escape(title))?;
+    writeln!(out, "<ul>")?;
+    if let Some(profile) = report.profile {
+        writeln!(out, "<li><b>Profile:</b> {}</li>", escape(profile))?;
+    }
+    writeln!(out, "<li><b>Host:</b> {}</li>", escape(report.host))?;
+    let (scope_key, scope_value) = super::scope_header(report);
+    writeln!(out, "<li><b>{scope_key}:</b> {}</li>", escape(&scope_value))?;
+    writeln!(
+        out,
+        "<li><b>Time Span:</b> {} - {}</li>",
+        report.after, report.before
+    )?;
+    writeln!(
+        out,
+        "<li><b>Total time:</b> {} ({} days with records)</li>",
+        format_duration(report.total_time()),
+        report.days_with_records()
+    )?;
+    writeln!(out, "</ul>")?;
+
+    for (week, nodes_by_day) in &report.weeks {
+        writeln!(
+            out,
+            "<h{level}>Week {}-W{:02}</h{level}>",
+            week.year(),
+            week.week(),
+            level = level + 1
+        )?;
+        writeln!(
+            out,
+            "<p><b>Total time:</b> {}</p>",
+            format_duration(week_total(nodes_by_day))
+        )?;
+
+        for (day, nodes_of_day) in nodes_by_day {
+            writeln!(
+                out,
+                "<h{level}>{day}, {weekday} ({total})</h{level}>",
+                level = level + 2,
+                weekday = day.weekday(),
+                total = format_duration(views::to_time_spent_sum(nodes_of_day))
+            )?;
+            write_timelog_table(out, nodes_of_day, super::shows_timelog_users(report))?;
+        }
+    }
+
+    write_extended_summary(out, report, level + 1)
+}
+
+/// Renders a self-contained HTML page with a table per day, the totals per
+/// week, and the summary per epic and per issue or merge request.
+#[derive(Debug)]
+pub struct HtmlRenderer;
+
+impl Renderer for HtmlRenderer {
+    fn render(&self, out: &mut dyn Write, report: &Report) -> anyhow::Result<()> {
+        write_page_head(
+            out,
+            &format!("Timesheet {} - {}", report.after, report.before),
+        )?;
+        write_report(out, report, "Timesheet", 1)?;
+        write_page_tail(out)?;
+        Ok(())
+    }
+
+    fn render_combined(&self, out: &mut dyn Write, reports: &[Report]) -> anyhow::Result<()> {
+        let titles = super::section_titles(reports);
+
+        write_page_head(out, "Timesheet")?;
+        writeln!(out, "<h1>Timesheet</h1>")?;
+        writeln!(
+            out,
+            "<p><b>Grand total:</b> {}</p>",
+            format_duration(super::grand_total(reports))
+        )?;
+        write_overview(out, reports, &titles)?;
+        for (report, title) in reports.iter().zip(&titles) {
+            write_report(out, report, title, 2)?;
+        }
+        write_page_tail(out)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gitlab_api::test_utils::node;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn write_html_escapes_and_links_issues() {
+        let nodes = [node(
+            "2024-06-03T12:00:00Z",
+            5400,
+            "<script> & co",
+            Some("Epic"),
+            None,
+        )];
+        let nodes = nodes.iter().collect::<Vec<_>>();
+
+        let report = Report::with_metadata(
+            &nodes,
+            "gitlab.example.com",
+            "user",
+            NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 6, 9).unwrap(),
+        );
+
+        let mut out = Vec::new();
+        HtmlRenderer.render(&mut out, &report).unwrap();
+        let html = String::from_utf8(out).unwrap();
+
+        assert!(!html.contains("<script>"));
+        assert!(html.contains(
+            "<a href=\"https://gitlab.example.com/-/issues/&lt;script&gt;-&amp;-co\">&lt;script&gt; &amp; co</a>"
+        ));
+        assert!(html.contains("<h2>Week 2024-W23</h2>"));
+        assert!(html.contains("<li><b>Total time:</b> 1h 30m (1 days with records)</li>"));
+        assert!(html.trim_end().ends_with("</html>"));
+    }
+
+    #[test]
+    fn write_html_combines_profiles_into_one_page() {
+        let nodes = [node("2024-06-03T12:00:00Z", 5400, "Issue", None, None)];
+        let nodes = nodes.iter().collect::<Vec<_>>();
+        let after = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        let before = NaiveDate::from_ymd_opt(2024, 6, 9).unwrap();
+
+        let mut reports = [
+            Report::with_metadata(&nodes, "gitlab.example.com", "user", after, before),
+            Report::with_metadata(&nodes, "customer.example", "user", after, before),
+        ];
+        reports[0].profile = Some("work");
+
+        let mut out = Vec::new();
+        HtmlRenderer.render_combined(&mut out, &reports).unwrap();
+        let html = String::from_utf8(out).unwrap();
+
+        assert_eq!(html.matches("<html>").count(), 1);
+        assert!(html.contains("<p><b>Grand total:</b> 3h 00m</p>"));
+        assert!(html.contains("<td class=\"missing\">missing</td>"));
+        assert!(html.contains("<h2>work</h2>"));
+        assert!(html.contains("<h2>customer.example</h2>"));
+        assert!(html.contains("<h3>Week 2024-W23</h3>"));
+    }
+}
@@ -0,0 +1,377 @@
+/*
+MIT License
+
+Copyright (c) 2024 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Machine-readable JSON output of all fetched timelogs.
+//!
+//! The document contains the raw timelogs as well as the aggregates that the
+//! [`views`] module computes (per week, per day, per epic, per issue). The
+//! schema is documented in the README and versioned by [`SCHEMA_VERSION`].
+//! All durations are given in seconds.
+
+use super::{Renderer, Report, week_total};
+use crate::fetch::QueryScope;
+use crate::gitlab_api::types::{ResponseNode, Target};
+use crate::views;
+use chrono::{Datelike, NaiveDate, SecondsFormat};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// Version of the JSON schema. Bumped on every incompatible change.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize)]
+struct Document<'a> {
+    schema_version: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    profile: Option<&'a str>,
+    host: &'a str,
+    /// `None` for groups and projects.
+    username: Option<&'a str>,
+    scope: Scope<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    display_name: Option<&'a str>,
+    time_span: TimeSpan,
+    total_secs: u64,
+    /// Only present if working hours are configured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_secs: Option<u64>,
+    /// Overtime balance of the whole time span. Only present if working
+    /// hours are configured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    balance_secs: Option<i64>,
+    days_with_records: usize,
+    /// Past workdays without logged time and without absence.
+    missing_days: Vec<NaiveDate>,
+    weeks: Vec<Week<'a>>,
+    epics: Vec<EpicSummary>,
+    targets: Vec<TargetSummary<'a>>,
+}
+
+/// Document of a combined timesheet of multiple profiles or users.
+#[derive(Debug, Serialize)]
+struct CombinedDocument<'a> {
+    schema_version: u32,
+    /// Grand total of all reports.
+    total_secs: u64,
+    reports: Vec<Document<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct Scope<'a> {
+    /// `user`, `group`, or `project`.
+    kind: &'static str,
+    /// Username or full path of the group or project.
+    name: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct TimeSpan {
+    after: NaiveDate,
+    before: NaiveDate,
+}
+
+#[derive(Debug, Serialize)]
+struct Week<'a> {
+    year: i32,
+    week: u32,
+    total_secs: u64,
+    /// Only present if working hours are configured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_secs: Option<u64>,
+    /// Running overtime balance at the end of the week. Only present if
+    /// working hours are configured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    balance_secs: Option<i64>,
+    days: Vec<Day<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct Day<'a> {
+    date: NaiveDate,
+    weekday: String,
+    total_secs: u64,
+    timelogs: Vec<Timelog<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct Timelog<'a> {
+    /// UTC timestamp as reported by GitLab, such as `2024-06-03T12:00:00Z`.
+    spent_at: String,
+    /// Signed, as GitLab allows negative timelogs.
+    time_spent_secs: i64,
+    summary: Option<&'a str>,
+    target: TargetRef<'a>,
+    group: Option<GroupRef<'a>>,
+    /// The user who logged the time.
+    user: Option<UserRef<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct UserRef<'a> {
+    username: &'a str,
+    name: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct TargetRef<'a> {
+    /// `issue` or `merge_request`.
+    kind: &'static str,
+    title: &'a str,
+    web_url: &'a str,
+    epic: Option<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+struct GroupRef<'a> {
+    full_name: &'a str,
+    full_path: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct EpicSummary {
+    /// `None` for all timelogs without an epic.
+    title: Option<String>,
+    total_secs: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct TargetSummary<'a> {
+    #[serde(flatten)]
+    target: TargetRef<'a>,
+    total_secs: u64,
+}
+
+impl<'a> From<&'a Target> for TargetRef<'a> {
+    fn from(target: &'a Target) -> Self {
+        Self {
+            kind: match target {
+                Target::Issue(_) => "issue",
+                Target::MergeRequest(_) => "merge_request",
+            },
+            title: target.title(),
+            web_url: target.web_url(),
+            epic: target.epic().map(|e| e.title.as_str()),
+        }
+    }
+}
+
+impl<'a> From<&'a ResponseNode> for Timelog<'a> {
+    fn from(node: &'a ResponseNode) -> Self {
+        Self {
+            spent_at: node.spentAt.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            time_spent_secs: node.timeSpent,
+            summary: node.summary.as_deref(),
+            target: TargetRef::from(&node.target),
+            group: node.project.group.as_ref().map(|group| GroupRef {
+                full_name: &group.fullName,
+                full_path: &group.fullPath,
+            }),
+            user: node.user.as_ref().map(|user| UserRef {
+                username: &user.username,
+                name: &user.name,
+            }),
+        }
+    }
+}
+
+fn to_days<'a>(nodes_by_day: &BTreeMap<NaiveDate, Vec<&'a ResponseNode>>) -> Vec<Day<'a>> {
+    nodes_by_day
+        .iter()
+        .map(|(date, nodes_of_day)| Day {
+            date: *date,
+            weekday: date.weekday().to_string(),
+            total_secs: views::to_time_spent_sum(nodes_of_day).as_secs(),
+            timelogs: nodes_of_day.iter().copied().map(Timelog::from).collect(),
+        })
+        .collect()
+}
+
+fn to_document<'a>(report: &'a Report<'a>) -> Document<'a> {
+    let weeks = report
+        .weeks
+        .iter()
+        .map(|(week, nodes_by_day)| Week {
+            year: week.year(),
+            week: week.week(),
+            total_secs: week_total(nodes_by_day).as_secs(),
+            target_secs: report.week_target(*week).map(|target| target.as_secs()),
+            balance_secs: report.balance_until(week.last_day()),
+            days: to_days(nodes_by_day),
+        })
+        .collect();
+
+    let epics = report
+        .epics
+        .iter()
+        .map(|(epic, nodes_of_epic)| EpicSummary {
+            title: epic.as_ref().map(|e| e.title.clone()),
+            total_secs: views::to_time_spent_sum(nodes_of_epic).as_secs(),
+        })
+        .collect();
+
+    let targets = report
+        .targets
+        .iter()
+        .map(|(target, nodes_of_target)| TargetSummary {
+            target: TargetRef::from(target),
+            total_secs: views::to_time_spent_sum(nodes_of_target).as_secs(),
+        })
+        .collect();
+
+    Document {
+        schema_version: SCHEMA_VERSION,
+        profile: report.profile,
+        host: report.host,
+        username: matches!(report.scope, QueryScope::User(_)).then_some(report.username),
+        scope: Scope {
+            kind: report.scope.kind(),
+            name: report.scope.name(),
+        },
+        display_name: report.display_name,
+        time_span: TimeSpan {
+            after: report.after,
+            before: report.before,
+        },
+        total_secs: report.total_time().as_secs(),
+        target_secs: report.total_target().map(|target| target.as_secs()),
+        balance_secs: report.balance_until(report.before),
+        days_with_records: report.days_with_records(),
+        missing_days: report.missing_days(),
+        weeks,
+        epics,
+        targets,
+    }
+}
+
+/// Renders all nodes and their aggregates as a single pretty-printed JSON
+/// document.
+#[derive(Debug)]
+pub struct JsonRenderer;
+
+impl Renderer for JsonRenderer {
+    fn render(&self, out: &mut dyn Write, report: &Report) -> anyhow::Result<()> {
+        serde_json::to_writer_pretty(&mut *out, &to_document(report))?;
+        writeln!(out)?;
+        Ok(())
+    }
+
+    fn render_combined(&self, out: &mut dyn Write, reports: &[Report]) -> anyhow::Result<()> {
+        let document = CombinedDocument {
+            schema_version: SCHEMA_VERSION,
+            total_secs: super::grand_total(reports).as_secs(),
+            reports: reports.iter().map(to_document).collect(),
+        };
+        serde_json::to_writer_pretty(&mut *out, &document)?;
+        writeln!(out)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gitlab_api::test_utils::{mr_node, node};
+    use serde_json::Value;
+
+    #[test]
+    fn json_contains_aggregates() {
+        let nodes = [
+            node("2024-06-03T12:00:00Z", 3600, "Issue A", Some("Epic"), None),
+            node("2024-06-03T13:00:00Z", 1800, "Issue B", None, None),
+            node("2024-06-11T12:00:00Z", 900, "Issue A", Some("Epic"), None),
+            mr_node("2024-06-11T13:00:00Z", 600, "MR"),
+        ];
+        let nodes = nodes.iter().collect::<Vec<_>>();
+
+        let report = Report::with_metadata(
+            &nodes,
+            "gitlab.example.com",
+            "user",
+            NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 6, 16).unwrap(),
+        );
+
+        let mut out = Vec::new();
+        JsonRenderer.render(&mut out, &report).unwrap();
+        let json = serde_json::from_slice::<Value>(&out).unwrap();
+
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        assert_eq!(json["host"], "gitlab.example.com");
+        assert_eq!(json["username"], "user");
+        assert_eq!(json["scope"]["kind"], "user");
+        assert_eq!(json["time_span"]["after"], "2024-06-03");
+        assert_eq!(json["total_secs"], 6900);
+        assert_eq!(json["days_with_records"], 2);
+        assert_eq!(json["missing_days"].as_array().unwrap().len(), 8);
+        assert_eq!(json["missing_days"][0], "2024-06-04");
+        assert_eq!(json["weeks"].as_array().unwrap().len(), 2);
+        assert_eq!(json["weeks"][0]["week"], 23);
+        assert_eq!(json["weeks"][0]["total_secs"], 5400);
+        assert_eq!(json["weeks"][0]["days"][0]["weekday"], "Mon");
+        assert_eq!(
+            json["weeks"][0]["days"][0]["timelogs"]
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(json["epics"][0]["title"], Value::Null);
+        assert_eq!(json["epics"][1]["total_secs"], 4500);
+        assert_eq!(json["targets"][0]["kind"], "issue");
+        assert_eq!(json["targets"][0]["title"], "Issue A");
+        assert_eq!(json["targets"][0]["total_secs"], 4500);
+        assert_eq!(json["targets"][2]["kind"], "merge_request");
+        assert_eq!(json["targets"][2]["epic"], Value::Null);
+        assert!(json.get("profile").is_none());
+        assert!(json.get("target_secs").is_none());
+        assert!(json["weeks"][0].get("balance_secs").is_none());
+    }
+
+    #[test]
+    fn json_combines_profiles() {
+        let work = [node("2024-06-03T12:00:00Z", 3600, "A", None, None)];
+        let work = work.iter().collect::<Vec<_>>();
+        let customer = [node("2024-06-04T12:00:00Z", 1800, "B", None, None)];
+        let customer = customer.iter().collect::<Vec<_>>();
+        let after = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        let before = NaiveDate::from_ymd_opt(2024, 6, 9).unwrap();
+
+        let mut reports = [
+            Report::with_metadata(&work, "gitlab.example.com", "user", after, before),
+            Report::with_metadata(&customer, "customer.example", "user", after, before),
+        ];
+        reports[0].profile = Some("work");
+        reports[1].profile = Some("customer");
+
+        let mut out = Vec::new();
+        JsonRenderer.render_combined(&mut out, &reports).unwrap();
+        let json = serde_json::from_slice::<Value>(&out).unwrap();
+
+        assert_eq!(json["total_secs"], 5400);
+        assert_eq!(json["reports"][0]["profile"], "work");
+        assert_eq!(json["reports"][0]["total_secs"], 3600);
+        assert_eq!(json["reports"][1]["host"], "customer.example");
+    }
+}
@@ -0,0 +1,359 @@
+/*
+MIT License
+
+Copyright (c) 2024 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Markdown timesheet report, e.g., for wikis or merge request descriptions.
+
+use super::{Renderer, Report, format_duration, target_prefix, week_total};
+use crate::gitlab_api::types::ResponseNode;
+use crate::views;
+use chrono::Datelike;
+use std::io::Write;
+
+/// Escapes text for the use inside a Markdown table cell.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '|' | '[' | ']' | '*' | '_' | '`' | '<' | '>' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("<br>"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escapes the characters of a URL that would terminate a Markdown link.
+fn escape_url(url: &str) -> String {
+    url.replace(' ', "%20")
+        .replace('(', "%28")
+        .replace(')', "%29")
+}
+
+/// Writes a table of the timelogs. With `show_user`, the table has an
+/// additional column for the user who logged the time.
+fn write_timelog_table(
+    out: &mut dyn Write,
+    nodes: &[&ResponseNode],
+    show_user: bool,
+) -> std::io::Result<()> {
+    let (user_header, user_delim) = if show_user {
+        (" User |", "---|")
+    } else {
+        ("", "")
+    };
+    writeln!(
+        out,
+        "| Duration | Issue / MR | Epic | Group |{user_header} Summary |"
+    )?;
+    writeln!(out, "|---:|---|---|---|{user_delim}---|")?;
+    for node in nodes {
+        let (is_positive, duration) = node.timeSpent();
+        let sign = if is_positive { "" } else { "-" };
+        writeln!(
+            out,
+            "| {sign}{duration} | {prefix}[{title}]({url}) | {epic} | {group} |{user} {summary} |",
+            prefix = target_prefix(&node.target),
+            duration = format_duration(duration),
+            title = escape(node.target.title()),
+            url = escape_url(node.target.web_url()),
+            epic = escape(node.epic_name().unwrap_or_default()),
+            group = escape(
+                node.project
+                    .group
+                    .as_ref()
+                    .map(|g| g.fullPath.as_str())
+                    .unwrap_or_default()
+            ),
+            user = if show_user {
+                format!(" {} |", escape(&super::timelog_user(node)))
+            } else {
+                String::new()
+            },
+            summary = escape(node.summary.as_deref().unwrap_or_default()),
+        )?;
+    }
+    Ok(())
+}
+
+/// Returns the Markdown prefix of a heading of the given level.
+fn heading(level: usize) -> String {
+    "#".repeat(level)
+}
+
+fn write_extended_summary(
+    out: &mut dyn Write,
+    report: &Report,
+    level: usize,
+) -> std::io::Result<()> {
+    writeln!(out, "{} Summary per Epic", heading(level))?;
+    writeln!(out)?;
+    writeln!(out, "| Duration | Epic |")?;
+    writeln!(out, "|---:|---|")?;
+    for (epic, nodes_of_epic) in &report.epics {
+        writeln!(
+            out,
+            "| {duration} | {epic} |",
+            duration = format_duration(views::to_time_spent_sum(nodes_of_epic)),
+            epic = escape(epic.as_ref().map_or("<No Epic>", |e| e.title.as_str())),
+        )?;
+    }
+    writeln!(out)?;
+
+    writeln!(
+        out,
+        "{} Summary per Issue and Merge Request",
+        heading(level)
+    )?;
+    writeln!(out)?;
+    writeln!(out, "| Duration | Issue / MR |")?;
+    writeln!(out, "|---:|---|")?;
+    for (target, nodes_of_target) in &report.targets {
+        writeln!(
+            out,
+            "| {duration} | {prefix}[{title}]({url}) |",
+            prefix = target_prefix(target),
+            duration = format_duration(views::to_time_spent_sum(nodes_of_target)),
+            title = escape(target.title()),
+            url = escape_url(target.web_url()),
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes the total per section and the matrix of sections and days of a
+/// combined timesheet. Working days without any time are marked as missing.
+fn write_overview(
+    out: &mut dyn Write,
+    reports: &[Report],
+    titles: &[String],
+) -> std::io::Result<()> {
+    writeln!(out, "| Name | Total | Days with records |")?;
+    writeln!(out, "|---|---:|---:|")?;
+    for (report, title) in reports.iter().zip(titles) {
+        writeln!(
+            out,
+            "| {title} | {} | {} |",
+            format_duration(report.total_time()),
+            report.days_with_records()
+        )?;
+    }
+    writeln!(out)?;
+
+    let days = super::span_days(reports);
+    write!(out, "| Name |")?;
+    for day in &days {
+        write!(out, " {} {} |", day.format("%m-%d"), day.weekday())?;
+    }
+    writeln!(out, " Total |")?;
+    writeln!(out, "|---|{}---:|", "---:|".repeat(days.len()))?;
+
+    for (report, title) in reports.iter().zip(titles) {
+        write!(out, "| {title} |")?;
+        for day in &days {
+            let total = report.day_total(*day);
+            if report.is_missing_day(*day) {
+                write!(out, " **missing** |")?;
+            } else if total.is_zero() {
+                write!(out, " - |")?;
+            } else {
+                write!(out, " {} |", format_duration(total))?;
+            }
+        }
+        writeln!(out, " {} |", format_duration(report.total_time()))?;
+    }
+
+    write!(out, "| **Total** |")?;
+    for day in &days {
+        let total = reports.iter().map(|report| report.day_total(*day)).sum();
+        write!(out, " {} |", format_duration(total))?;
+    }
+    writeln!(out, " {} |", format_duration(super::grand_total(reports)))
+}
+
+/// Writes the report with `title` as heading of the given level. The
+/// sections of the report use the levels below.
+fn write_report(
+    out: &mut dyn Write,
+    report: &Report,
+    title: &str,
+    level: usize,
+) -> std::io::Result<()> {
+    writeln!(out, "{} {title}", heading(level))?;
+    writeln!(out)?;
+    if let Some(profile) = report.profile {
+        writeln!(out, "- **Profile:** {}", escape(profile))?;
+    }
+    writeln!(out, "- **Host:** {}", escape(report.host))?;
+    let (scope_key, scope_value) = super::scope_header(report);
+    writeln!(out, "- **{scope_key}:** {}", escape(&scope_value))?;
+    writeln!(out, "- **Time Span:** {} - {}", report.after, report.before)?;
+    writeln!(
+        out,
+        "- **Total time:** {} ({} days with records)",
+        format_duration(report.total_time()),
+        report.days_with_records()
+    )?;
+    writeln!(out)?;
+
+    for (week, nodes_by_day) in &report.weeks {
+        writeln!(
+            out,
+            "{} Week {}-W{:02}",
+            heading(level + 1),
+            week.year(),
+            week.week()
+        )?;
+        writeln!(out)?;
+        writeln!(
+            out,
+            "**Total time:** {}",
+            format_duration(week_total(nodes_by_day))
+        )?;
+        writeln!(out)?;
+
+        for (day, nodes_of_day) in nodes_by_day {
+            writeln!(
+                out,
+                "{heading} {day}, {weekday} ({total})",
+                heading = heading(level + 2),
+                weekday = day.weekday(),
+                total = format_duration(views::to_time_spent_sum(nodes_of_day))
+            )?;
+            writeln!(out)?;
+            write_timelog_table(out, nodes_of_day, super::shows_timelog_users(report))?;
+            writeln!(out)?;
+        }
+    }
+
+    write_extended_summary(out, report, level + 1)
+}
+
+/// Renders a Markdown timesheet with a table per day, the totals per week,
+/// and the summary per epic and per issue or merge request.
+#[derive(Debug)]
+pub struct MarkdownRenderer;
+
+impl Renderer for MarkdownRenderer {
+    fn render(&self, out: &mut dyn Write, report: &Report) -> anyhow::Result<()> {
+        write_report(out, report, "Timesheet", 1)?;
+        Ok(())
+    }
+
+    fn render_combined(&self, out: &mut dyn Write, reports: &[Report]) -> anyhow::Result<()> {
+        let titles = super::section_titles(reports)
+            .iter()
+            .map(|title| escape(title))
+            .collect::<Vec<_>>();
+
+        writeln!(out, "# Timesheet")?;
+        writeln!(out)?;
+        writeln!(
+            out,
+            "- **Grand total:** {}",
+            format_duration(super::grand_total(reports))
+        )?;
+        writeln!(out)?;
+        write_overview(out, reports, &titles)?;
+        writeln!(out)?;
+
+        for (report, title) in reports.iter().zip(&titles) {
+            write_report(out, report, title, 2)?;
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gitlab_api::test_utils::node;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn escape_handles_table_syntax() {
+        assert_eq!(escape("a | b"), "a \\| b");
+        assert_eq!(escape("[WIP] x"), "\\[WIP\\] x");
+        assert_eq!(escape("line1\r\nline2"), "line1<br>line2");
+    }
+
+    #[test]
+    fn write_markdown_contains_tables_and_totals() {
+        let nodes = [
+            node("2024-06-03T12:00:00Z", 5400, "Issue A", Some("Epic"), None),
+            node("2024-06-04T12:00:00Z", 1800, "Issue B", None, Some("team")),
+        ];
+        let nodes = nodes.iter().collect::<Vec<_>>();
+
+        let report = Report::with_metadata(
+            &nodes,
+            "gitlab.example.com",
+            "user",
+            NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 6, 9).unwrap(),
+        );
+
+        let mut out = Vec::new();
+        MarkdownRenderer.render(&mut out, &report).unwrap();
+        let md = String::from_utf8(out).unwrap();
+
+        assert!(md.contains("- **Total time:** 2h 00m (2 days with records)"));
+        assert!(md.contains("## Week 2024-W23"));
+        assert!(md.contains("### 2024-06-03, Mon (1h 30m)"));
+        assert!(md.contains(
+            "| 1h 30m | [Issue A](https://gitlab.example.com/-/issues/Issue-A) | Epic |  |  |"
+        ));
+        assert!(md.contains("| 0h 30m | \\<No Epic\\> |"));
+    }
+
+    #[test]
+    fn write_markdown_combined_marks_missing_days() {
+        let nodes = [node("2024-06-03T12:00:00Z", 5400, "Issue A", None, None)];
+        let nodes = nodes.iter().collect::<Vec<_>>();
+        let after = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        let before = NaiveDate::from_ymd_opt(2024, 6, 4).unwrap();
+        let reports = [
+            Report::with_metadata(&nodes, "gitlab.example.com", "alice", after, before),
+            Report::with_metadata(&[], "gitlab.example.com", "bob", after, before),
+        ];
+
+        let mut out = Vec::new();
+        MarkdownRenderer
+            .render_combined(&mut out, &reports)
+            .unwrap();
+        let md = String::from_utf8(out).unwrap();
+
+        assert!(md.contains("- **Grand total:** 1h 30m"));
+        assert!(md.contains("| alice | 1h 30m | 1 |"));
+        assert!(md.contains("| Name | 06-03 Mon | 06-04 Tue | Total |"));
+        assert!(md.contains("| alice | 1h 30m | **missing** | 1h 30m |"));
+        assert!(md.contains("| **Total** | 1h 30m | 0h 00m | 1h 30m |"));
+        assert!(md.contains("## bob"));
+        assert!(md.contains("### Week 2024-W23"));
+    }
+}
@@ -0,0 +1,530 @@
+/*
+MIT License
+
+Copyright (c) 2024 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Renderers for the fetched timelogs.
+//!
+//! All renderers implement [`Renderer`] and write a [`Report`] to an
+//! arbitrary [`Write`]r. [`renderer`] is the entry point.
+
+pub mod check;
+pub mod csv;
+pub mod html;
+pub mod json;
+pub mod markdown;
+pub mod terminal;
+
+use crate::absences::Absences;
+use crate::calendar::{Calendar, Week};
+use crate::cli::{CliArgs, OutputFormat};
+use crate::duration_to_hhmm;
+use crate::fetch::QueryScope;
+use crate::gitlab_api::types::{Epic, ResponseNode, Target};
+use crate::rules::{Finding, Rules};
+use crate::views;
+use crate::working_hours::{Workdays, WorkingHours};
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::io::Write;
+use std::time::Duration;
+
+/// The timelogs of a query together with their grouped views, prepared for
+/// rendering.
+#[derive(Debug)]
+pub struct Report<'a> {
+    /// Name of the profile of the config file, if any.
+    pub profile: Option<&'a str>,
+    pub host: &'a str,
+    pub username: &'a str,
+    /// Display name of the user, if known.
+    pub display_name: Option<&'a str>,
+    /// Whose timelogs are shown. For groups and projects, the timelogs are
+    /// by anyone and `username` is empty.
+    pub scope: QueryScope<'a>,
+    /// Inclusive begin of the queried time span.
+    pub after: NaiveDate,
+    /// Inclusive end of the queried time span.
+    pub before: NaiveDate,
+    /// The sanity-check rules, applied by renderers that show findings.
+    pub rules: Rules,
+    /// The target working time, if configured.
+    pub working_hours: Option<WorkingHours>,
+    /// The days of the week on which work is expected.
+    pub workdays: Workdays,
+    /// Holidays, vacation, and sick days.
+    pub absences: Absences,
+    /// The time zone and the first day of the week.
+    pub calendar: Calendar,
+    /// The current day. Later days have no target working time yet.
+    pub today: NaiveDate,
+    /// Whether the summary per epic and per issue should be rendered.
+    /// Renderers for reports may ignore this and always render it.
+    pub extended_summary: bool,
+    pub nodes: Vec<&'a ResponseNode>,
+    /// The nodes per week and per day.
+    pub weeks: BTreeMap<Week, BTreeMap<NaiveDate, Vec<&'a ResponseNode>>>,
+    pub epics: BTreeMap<Option<Epic>, Vec<&'a ResponseNode>>,
+    /// The nodes per issue and per merge request.
+    pub targets: BTreeMap<Target, Vec<&'a ResponseNode>>,
+}
+
+impl<'a> Report<'a> {
+    /// Creates a report of the given nodes with the metadata from the CLI.
+    pub fn new(nodes: &[&'a ResponseNode], cfg: &'a CliArgs) -> Self {
+        let mut report = Self::with_metadata(nodes, cfg.host(), "", cfg.after(), cfg.before());
+        if let Some(scope) = cfg.scope() {
+            report.scope = scope;
+        }
+        if let QueryScope::User(username) = report.scope {
+            report.username = username;
+        }
+        report.profile = cfg.profile();
+        report.display_name = cfg.display_name();
+        report.extended_summary = cfg.print_extended_summary();
+        report.rules = cfg.rules().clone();
+        report.working_hours = cfg.working_hours().copied();
+        report.workdays = cfg.workdays();
+        report.absences = cfg.absences().clone();
+        report.set_calendar(cfg.calendar());
+        report
+    }
+
+    /// Creates a report of the given nodes with the given metadata.
+    pub fn with_metadata(
+        nodes: &[&'a ResponseNode],
+        host: &'a str,
+        username: &'a str,
+        after: NaiveDate,
+        before: NaiveDate,
+    ) -> Self {
+        let calendar = Calendar::default();
+        Self {
+            profile: None,
+            host,
+            username,
+            display_name: None,
+            scope: QueryScope::User(username),
+            after,
+            before,
+            extended_summary: false,
+            rules: Rules::default(),
+            working_hours: None,
+            workdays: Workdays::default(),
+            absences: Absences::default(),
+            calendar,
+            today: calendar.today(),
+            nodes: nodes.to_vec(),
+            weeks: to_weeks(nodes, calendar),
+            epics: views::to_nodes_by_epic(nodes),
+            targets: views::to_nodes_by_target(nodes),
+        }
+    }
+
+    /// Sets the timezone and the first day of the week, and groups the nodes
+    /// accordingly.
+    pub fn set_calendar(&mut self, calendar: Calendar) {
+        self.calendar = calendar;
+        self.today = calendar.today();
+        self.weeks = to_weeks(&self.nodes, calendar);
+    }
+
+    /// Returns the total time of all nodes.
+    pub fn total_time(&self) -> Duration {
+        views::to_time_spent_sum(&self.nodes)
+    }
+
+    /// Returns the number of days with at least one record.
+    pub fn days_with_records(&self) -> usize {
+        self.weeks.values().map(BTreeMap::len).sum()
+    }
+
+    /// Returns the total time of the given day.
+    pub fn day_total(&self, day: NaiveDate) -> Duration {
+        self.weeks
+            .get(&self.calendar.week_of(day))
+            .and_then(|nodes_by_day| nodes_by_day.get(&day))
+            .map_or(Duration::ZERO, |nodes| views::to_time_spent_sum(nodes))
+    }
+
+    /// Returns whether no time is logged on a past workday without absence.
+    /// Today is not missing yet, as it may not be logged yet.
+    pub fn is_missing_day(&self, day: NaiveDate) -> bool {
+        day < self.today
+            && self.workdays.contains(day)
+            && self.absences.get(day).is_none()
+            && self.day_total(day).is_zero()
+    }
+
+    /// Returns all missing days of the time span, see
+    /// [`Self::is_missing_day`].
+    pub fn missing_days(&self) -> Vec<NaiveDate> {
+        self.after
+            .iter_days()
+            .take_while(|day| *day <= self.before)
+            .filter(|day| self.is_missing_day(*day))
+            .collect()
+    }
+
+    /// Returns all weeks that overlap with the time span, including weeks
+    /// without records.
+    pub fn span_weeks(&self) -> Vec<Week> {
+        let mut weeks = self
+            .after
+            .iter_days()
+            .take_while(|day| *day <= self.before)
+            .map(|day| self.calendar.week_of(day))
+            .collect::<Vec<_>>();
+        weeks.dedup();
+        weeks
+    }
+
+    /// Returns the days of the time span that have a target working time,
+    /// i.e., all days up to today.
+    fn target_days(&self) -> impl Iterator<Item = NaiveDate> {
+        let end = self.before.min(self.today);
+        self.after.iter_days().take_while(move |day| *day <= end)
+    }
+
+    /// Returns the target working time of the day, which is zero on days
+    /// of absence, or `None` if no working hours are configured.
+    ///
+    /// As the current day isn't over yet, its target only counts as far as
+    /// time is logged already. Hence, the balance doesn't turn negative in
+    /// the middle of the day.
+    fn target(&self, day: NaiveDate) -> Option<Duration> {
+        let working_hours = self.working_hours?;
+        let target = if self.absences.get(day).is_some() {
+            Duration::ZERO
+        } else {
+            working_hours.target(day, self.workdays)
+        };
+        Some(if day == self.today {
+            target.min(self.day_total(day))
+        } else {
+            target
+        })
+    }
+
+    /// Returns the target working time of the whole time span, or `None` if
+    /// no working hours are configured.
+    pub fn total_target(&self) -> Option<Duration> {
+        self.working_hours?;
+        self.target_days().map(|day| self.target(day)).sum()
+    }
+
+    /// Returns the target working time of the days of the given week within
+    /// the time span.
+    pub fn week_target(&self, week: Week) -> Option<Duration> {
+        self.working_hours?;
+        self.target_days()
+            .filter(|day| week.contains(*day))
+            .map(|day| self.target(day))
+            .sum()
+    }
+
+    /// Returns the overtime balance in seconds, i.e., the logged time minus
+    /// the target working time of all days of the time span up to `end`.
+    pub fn balance_until(&self, end: NaiveDate) -> Option<i64> {
+        self.working_hours?;
+        let target = self
+            .target_days()
+            .take_while(|day| *day <= end)
+            .map(|day| self.target(day))
+            .sum::<Option<Duration>>()?;
+        let logged = self
+            .days()
+            .take_while(|(day, _)| **day <= end)
+            .map(|(_, nodes)| views::to_time_spent_sum(nodes))
+            .sum::<Duration>();
+        Some(signed_secs(logged) - signed_secs(target))
+    }
+
+    /// Checks the timelogs of a day with records.
+    pub fn check_day(&self, day: NaiveDate, nodes: &[&ResponseNode]) -> Vec<Finding<'static>> {
+        self.rules.check_day(
+            day,
+            nodes,
+            self.workdays.contains(day),
+            self.absences.get(day),
+        )
+    }
+
+    /// Returns the findings of the sanity-check rules, ordered by date.
+    pub fn findings(&self) -> Vec<Finding<'a>> {
+        let mut findings = Vec::new();
+        for (week, nodes_by_day) in &self.weeks {
+            findings.extend(self.rules.check_week(*week, nodes_by_day));
+            for (day, nodes) in nodes_by_day {
+                findings.extend(self.check_day(*day, nodes));
+                for &node in nodes {
+                    findings.extend(self.rules.check_timelog(*day, node));
+                }
+            }
+        }
+        for day in self.missing_days() {
+            findings.extend(self.rules.check_missing_day(day));
+        }
+        findings.sort_by_key(|finding| finding.subject.date());
+        findings
+    }
+
+    /// Returns the nodes per day, across all weeks.
+    pub fn days(&self) -> impl Iterator<Item = (&NaiveDate, &Vec<&'a ResponseNode>)> {
+        self.weeks.values().flatten()
+    }
+}
+
+/// A renderer turns a [`Report`] into a specific output format.
+pub trait Renderer: Debug {
+    /// Renders the report into `out`.
+    fn render(&self, out: &mut dyn Write, report: &Report) -> anyhow::Result<()>;
+
+    /// Renders the reports of multiple profiles into `out` as a single
+    /// combined timesheet with one section per report and a grand total.
+    fn render_combined(&self, out: &mut dyn Write, reports: &[Report]) -> anyhow::Result<()>;
+}
+
+/// Returns the renderer for the given output format.
+///
+/// `colored` only affects [`OutputFormat::Text`].
+pub fn renderer(format: OutputFormat, colored: bool) -> Box<dyn Renderer> {
+    match format {
+        OutputFormat::Text => Box::new(terminal::TerminalRenderer { colored }),
+        OutputFormat::Json => Box::new(json::JsonRenderer),
+        OutputFormat::Csv => Box::new(csv::CsvRenderer),
+        OutputFormat::Markdown => Box::new(markdown::MarkdownRenderer),
+        OutputFormat::Html => Box::new(html::HtmlRenderer),
+    }
+}
+
+/// Returns the nodes per week and per day.
+fn to_weeks<'a>(
+    nodes: &[&'a ResponseNode],
+    calendar: Calendar,
+) -> BTreeMap<Week, BTreeMap<NaiveDate, Vec<&'a ResponseNode>>> {
+    views::to_nodes_by_week(nodes, calendar)
+        .into_iter()
+        .map(|(week, nodes_of_week)| {
+            let nodes_by_day = views::to_nodes_by_day(&nodes_of_week, calendar.timezone);
+            (week, nodes_by_day)
+        })
+        .collect()
+}
+
+/// Returns the total time of a week.
+fn week_total(nodes_by_day: &BTreeMap<NaiveDate, Vec<&ResponseNode>>) -> Duration {
+    nodes_by_day
+        .values()
+        .map(|nodes| views::to_time_spent_sum(nodes))
+        .sum()
+}
+
+/// Returns the total time of all reports.
+fn grand_total(reports: &[Report]) -> Duration {
+    reports.iter().map(Report::total_time).sum()
+}
+
+/// Returns the titles of the sections of a combined timesheet, one per report.
+///
+/// The titles consist of the profile (or host) and the username, each only if
+/// it differs between the reports.
+fn section_titles(reports: &[Report]) -> Vec<String> {
+    let source = |report: &Report| report.profile.unwrap_or(report.host).to_string();
+    let differs = |f: &dyn Fn(&Report) -> String| {
+        reports
+            .first()
+            .is_some_and(|first| reports.iter().any(|report| f(report) != f(first)))
+    };
+    let show_user = differs(&|report| report.username.to_string());
+    let show_source = differs(&source) || !show_user;
+
+    reports
+        .iter()
+        .map(|report| {
+            let mut parts = Vec::new();
+            if show_source {
+                parts.push(source(report));
+            }
+            if show_user {
+                parts.push(report.username.to_string());
+            }
+            parts.join("/")
+        })
+        .collect()
+}
+
+/// Returns all days of the time span of the reports, e.g., for the matrix of
+/// sections and days of a combined timesheet.
+fn span_days(reports: &[Report]) -> Vec<NaiveDate> {
+    let after = reports.iter().map(|report| report.after).min();
+    let before = reports.iter().map(|report| report.before).max();
+    match (after, before) {
+        (Some(after), Some(before)) => after.iter_days().take_while(|day| *day <= before).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the key and value that describe whose timelogs are shown, for the
+/// header of reports, such as `Username` and the username with the display
+/// name.
+fn scope_header(report: &Report) -> (&'static str, String) {
+    match report.scope {
+        QueryScope::User(username) => (
+            "Username",
+            report.display_name.map_or_else(
+                || username.to_string(),
+                |name| format!("{username} ({name})"),
+            ),
+        ),
+        QueryScope::Group(path) => ("Group", path.to_string()),
+        QueryScope::Project(path) => ("Project", path.to_string()),
+    }
+}
+
+/// Returns whether the user should be shown for each timelog, as the timelogs
+/// may be by anyone.
+const fn shows_timelog_users(report: &Report) -> bool {
+    !matches!(report.scope, QueryScope::User(_))
+}
+
+/// Returns the username and name of the user who logged the time, such as
+/// `jdoe (Jane Doe)`.
+fn timelog_user(node: &ResponseNode) -> String {
+    node.user
+        .as_ref()
+        .map(|user| format!("{} ({})", user.username, user.name))
+        .unwrap_or_default()
+}
+
+/// Returns the prefix for the title of a target in reports, so that merge
+/// requests can be distinguished from issues.
+const fn target_prefix(target: &Target) -> &'static str {
+    match target {
+        Target::Issue(_) => "",
+        Target::MergeRequest(_) => "MR: ",
+    }
+}
+
+/// Formats a duration as `1h 05m` for reports.
+fn format_duration(duration: Duration) -> String {
+    let (hours, minutes) = duration_to_hhmm(duration);
+    format!("{hours}h {minutes:02}m")
+}
+
+/// Returns the duration in seconds, as signed number for balances.
+fn signed_secs(duration: Duration) -> i64 {
+    i64::try_from(duration.as_secs()).unwrap_or(i64::MAX)
+}
+
+/// Formats a balance in seconds with its sign, such as `+1h 30m`.
+fn format_balance(secs: i64) -> String {
+    let sign = if secs < 0 { '-' } else { '+' };
+    format!(
+        "{sign}{}",
+        format_duration(Duration::from_secs(secs.unsigned_abs()))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::{TimeZone, WeekStart};
+    use crate::gitlab_api::test_utils::node;
+
+    #[test]
+    fn section_titles_only_contain_differing_parts() {
+        let after = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        let before = NaiveDate::from_ymd_opt(2024, 6, 9).unwrap();
+        let report = |host, username| Report::with_metadata(&[], host, username, after, before);
+
+        let team = [report("a.example", "alice"), report("a.example", "bob")];
+        assert_eq!(section_titles(&team), ["alice", "bob"]);
+
+        let mut profiles = [report("a.example", "alice"), report("b.example", "alice")];
+        profiles[0].profile = Some("work");
+        assert_eq!(section_titles(&profiles), ["work", "b.example"]);
+
+        let both = [report("a.example", "alice"), report("b.example", "bob")];
+        assert_eq!(section_titles(&both), ["a.example/alice", "b.example/bob"]);
+    }
+
+    #[test]
+    fn day_total_and_missing_days() {
+        let nodes = [node("2024-06-03T12:00:00Z", 3600, "Issue", None, None)];
+        let nodes = nodes.iter().collect::<Vec<_>>();
+        let monday = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        let tuesday = NaiveDate::from_ymd_opt(2024, 6, 4).unwrap();
+        let sunday = NaiveDate::from_ymd_opt(2024, 6, 9).unwrap();
+        let mut report = Report::with_metadata(&nodes, "a.example", "alice", monday, sunday);
+
+        assert_eq!(report.day_total(monday), Duration::from_secs(3600));
+        assert!(!report.is_missing_day(monday));
+        assert!(report.is_missing_day(tuesday));
+        assert!(!report.is_missing_day(sunday));
+
+        let config = toml::from_str("vacation = [\"2024-06-04\"]").unwrap();
+        report.absences = Absences::from_config(&config, TimeZone::Local).unwrap();
+        assert!(!report.is_missing_day(tuesday));
+        assert_eq!(span_days(&[report]).len(), 7);
+    }
+
+    #[test]
+    fn calendar_determines_days_and_weeks() {
+        // Sunday evening in New York, but Monday in UTC.
+        let nodes = [node("2024-06-03T02:00:00Z", 3600, "Issue", None, None)];
+        let nodes = nodes.iter().collect::<Vec<_>>();
+        let sunday = NaiveDate::from_ymd_opt(2024, 6, 2).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        let mut report = Report::with_metadata(&nodes, "a.example", "alice", sunday, monday);
+
+        report.set_calendar(Calendar {
+            timezone: "UTC".parse().unwrap(),
+            week_start: WeekStart::Monday,
+        });
+        assert_eq!(report.day_total(monday), Duration::from_secs(3600));
+        assert_eq!(
+            report
+                .weeks
+                .keys()
+                .map(|week| week.first_day())
+                .collect::<Vec<_>>(),
+            [monday]
+        );
+
+        report.set_calendar(Calendar {
+            timezone: "America/New_York".parse().unwrap(),
+            week_start: WeekStart::Sunday,
+        });
+        assert_eq!(report.day_total(sunday), Duration::from_secs(3600));
+        assert_eq!(report.day_total(monday), Duration::ZERO);
+        assert_eq!(
+            report
+                .weeks
+                .keys()
+                .map(|week| week.first_day())
+                .collect::<Vec<_>>(),
+            [sunday]
+        );
+    }
+}
@@ -0,0 +1,846 @@
+/*
+MIT License
+
+Copyright (c) 2024 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! The default colored and human-readable output for the terminal.
+
+use super::{Renderer, Report, week_total};
+use crate::calendar::Week;
+use crate::duration_to_hhmm;
+use crate::gitlab_api::types::ResponseNode;
+use crate::rules::{Finding, RuleId, Rules, Severity};
+use crate::views;
+use chrono::{Datelike, NaiveDate};
+use nu_ansi_term::{Color, Style};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// Width of a duration, such as ` 1h 30m`.
+const CELL_WIDTH: usize = 7;
+
+/// Width of a day, such as `2024-06-03, Mon`.
+const DAY_WIDTH: usize = 15;
+
+/// Renders the timelogs grouped by week and day, including warnings for
+/// typical mistakes.
+#[derive(Debug)]
+pub struct TerminalRenderer {
+    /// Whether ANSI colors and styles are emitted.
+    pub colored: bool,
+}
+
+impl TerminalRenderer {
+    /// Returns the given style, or the plain style if colors are disabled.
+    fn style(&self, style: Style) -> Style {
+        if self.colored { style } else { Style::new() }
+    }
+
+    fn write_warning(&self, out: &mut dyn Write, msg: &str, indention: usize) -> io::Result<()> {
+        writeln!(
+            out,
+            "{indention}{msg}",
+            indention = " ".repeat(indention),
+            msg = self.style(Style::new().bold().fg(Color::Yellow)).paint(msg),
+        )
+    }
+
+    /// Writes a finding of a rule with the `^` pointing at the relevant
+    /// column of the line above.
+    fn write_finding(&self, out: &mut dyn Write, finding: &Finding) -> io::Result<()> {
+        let indention = match finding.rule {
+            RuleId::NegativeDuration => 3,
+            RuleId::ShortTimelog | RuleId::MissingSummary | RuleId::MissingEpic => 6,
+            RuleId::Weekend | RuleId::MissingDay => 12,
+            RuleId::LongDay | RuleId::DailyMinimum | RuleId::WeeklyMaximum => 18,
+        };
+        let color = match finding.severity {
+            Severity::Info => Color::Blue,
+            Severity::Warn => Color::Yellow,
+            Severity::Error => Color::Red,
+        };
+        writeln!(
+            out,
+            "{indention}{msg}",
+            indention = " ".repeat(indention),
+            msg = self
+                .style(Style::new().bold().fg(color))
+                .paint(format!("^ {}: {}", finding.severity, finding.message)),
+        )
+    }
+
+    /// Writes the target working time and the delta of the logged time to
+    /// it, with the key padded to `width`.
+    fn write_target(
+        &self,
+        out: &mut dyn Write,
+        key: &str,
+        width: usize,
+        logged: Duration,
+        target: Duration,
+    ) -> io::Result<()> {
+        write!(
+            out,
+            "{key}{padding} ",
+            key = self.style(Style::new().bold()).paint(key),
+            padding = " ".repeat(width.saturating_sub(key.len() + 1)),
+        )?;
+        self.write_duration(out, target, Color::Blue)?;
+        let delta = super::signed_secs(logged) - super::signed_secs(target);
+        writeln!(out, "  ({})", super::format_balance(delta))
+    }
+
+    /// Writes the overtime balance, with the key padded to `width`.
+    fn write_balance(
+        &self,
+        out: &mut dyn Write,
+        key: &str,
+        width: usize,
+        balance: i64,
+    ) -> io::Result<()> {
+        let color = if balance < 0 {
+            Color::Red
+        } else {
+            Color::Green
+        };
+        writeln!(
+            out,
+            "{key}{padding} {balance}",
+            key = self.style(Style::new().bold()).paint(key),
+            padding = " ".repeat(width.saturating_sub(key.len() + 1)),
+            balance = self
+                .style(Style::new().bold().fg(color))
+                .paint(format!("{:>7}", super::format_balance(balance))),
+        )
+    }
+
+    fn write_duration(
+        &self,
+        out: &mut dyn Write,
+        duration: Duration,
+        color: Color,
+    ) -> io::Result<()> {
+        let (hours, minutes) = duration_to_hhmm(duration);
+        let print_str = format!("{hours:>2}h {minutes:02}m");
+        write!(
+            out,
+            "{}",
+            self.style(Style::new().bold().fg(color)).paint(print_str)
+        )
+    }
+
+    fn write_timelog(
+        &self,
+        out: &mut dyn Write,
+        day: NaiveDate,
+        log: &ResponseNode,
+        rules: &Rules,
+        show_user: bool,
+    ) -> io::Result<()> {
+        let (_, duration) = log.timeSpent();
+        write!(out, "  ")?;
+        self.write_duration(out, duration, Color::Magenta)?;
+        writeln!(
+            out,
+            "  {prefix}{title}",
+            prefix = super::target_prefix(&log.target),
+            title = self
+                .style(Style::new().bold().fg(Color::Green))
+                .paint(log.target.title()),
+        )?;
+        for finding in rules.check_timelog(day, log) {
+            self.write_finding(out, &finding)?;
+        }
+
+        // Print issue metadata.
+        let epic_name = log.epic_name().unwrap_or("<no epic>");
+        let whitespace = " ".repeat(11);
+        writeln!(
+            out,
+            "{whitespace}{link}",
+            link = self
+                .style(Style::new().dimmed())
+                .paint(log.target.web_url())
+        )?;
+        if let Some(group) = &log.project.group {
+            writeln!(
+                out,
+                "{whitespace}[{epic_key} {epic_name}, {group_key} {group_name}]",
+                epic_key = self.style(Style::new().dimmed()).paint("Epic:"),
+                epic_name = self.style(Style::new().bold()).paint(epic_name),
+                group_key = self.style(Style::new().dimmed()).paint("Group:"),
+                group_name = self.style(Style::new().bold()).paint(&group.fullName),
+                whitespace = " ".repeat(11),
+            )?;
+        }
+
+        if let Some(user) = log.user.as_ref().filter(|_| show_user) {
+            writeln!(
+                out,
+                "{whitespace}[{user_key} {username} ({name})]",
+                user_key = self.style(Style::new().dimmed()).paint("User:"),
+                username = self.style(Style::new().bold()).paint(&user.username),
+                name = user.name,
+            )?;
+        }
+
+        if let Some(lines) = log.summary.as_ref().map(|t| t.lines()) {
+            for line in lines {
+                writeln!(out, "             {line}")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_date(
+        &self,
+        out: &mut dyn Write,
+        report: &Report,
+        day: &NaiveDate,
+        nodes_of_day: &[&ResponseNode],
+    ) -> io::Result<()> {
+        let rules = &report.rules;
+        let show_user = super::shows_timelog_users(report);
+        let absence = report.absences.get(*day);
+        let total = views::to_time_spent_sum(nodes_of_day);
+
+        let day_print = format!("{day}, {}", day.weekday());
+
+        write!(
+            out,
+            "{}  (",
+            self.style(Style::new().bold()).paint(day_print)
+        )?;
+        self.write_duration(out, total, Color::Blue)?;
+        write!(out, ")")?;
+        if let Some(absence) = absence {
+            write!(
+                out,
+                "  {}",
+                self.style(Style::new().bold().fg(Color::Cyan))
+                    .paint(format!("[{absence}]"))
+            )?;
+        } else if report.is_missing_day(*day) {
+            write!(
+                out,
+                "  {}",
+                self.style(Style::new().bold().fg(Color::Yellow))
+                    .paint("[MISSING: No time logged!]")
+            )?;
+        }
+        writeln!(out)?;
+
+        // Days of absence and missing days are also shown without records.
+        if !nodes_of_day.is_empty() {
+            for finding in report.check_day(*day, nodes_of_day) {
+                self.write_finding(out, &finding)?;
+            }
+        }
+
+        for log in nodes_of_day {
+            self.write_timelog(out, *day, log, rules, show_user)?;
+        }
+        Ok(())
+    }
+
+    fn write_week(
+        &self,
+        out: &mut dyn Write,
+        report: &Report,
+        week: Week,
+        nodes_by_day: &BTreeMap<NaiveDate, Vec<&ResponseNode>>,
+        days: &[(NaiveDate, &[&ResponseNode])],
+    ) -> io::Result<()> {
+        let rules = &report.rules;
+        let week_style = self.style(Style::new().bold());
+        let week_print = format!("WEEK {week}");
+        writeln!(
+            out,
+            "{delim} {week_print} {delim}",
+            delim = week_style.paint("======================"),
+            week_print = week_style.paint(week_print)
+        )?;
+        let total_week_time = week_total(nodes_by_day);
+        write!(
+            out,
+            "{total_time_key}       ",
+            total_time_key = self.style(Style::new().bold()).paint("Total time:")
+        )?;
+        self.write_duration(out, total_week_time, Color::Blue)?;
+        writeln!(out)?;
+        if let (Some(target), Some(balance)) = (
+            report.week_target(week),
+            report.balance_until(week.last_day()),
+        ) {
+            self.write_target(out, "Target:", 18, total_week_time, target)?;
+            self.write_balance(out, "Balance:", 18, balance)?;
+        }
+        for finding in rules.check_week(week, nodes_by_day) {
+            self.write_finding(out, &finding)?;
+        }
+        writeln!(out)?;
+
+        for (i, (day, nodes)) in days.iter().enumerate() {
+            self.write_date(out, report, day, nodes)?;
+
+            let is_last = i == days.len() - 1;
+            if !is_last {
+                writeln!(out)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_extended_summary(&self, out: &mut dyn Write, report: &Report) -> io::Result<()> {
+        for (epic, nodes_of_epic) in &report.epics {
+            let duration = views::to_time_spent_sum(nodes_of_epic);
+            write!(out, "  ")?;
+            self.write_duration(out, duration, Color::Magenta)?;
+            writeln!(
+                out,
+                " - {epic_key}  {epic_name}",
+                epic_key = self.style(Style::new().dimmed()).paint("Epic:"),
+                epic_name = self.style(Style::new().bold()).paint(
+                    epic.as_ref()
+                        .map(|e| e.title.as_str())
+                        .unwrap_or("<No Epic>")
+                )
+            )?;
+        }
+        for (target, nodes_of_target) in &report.targets {
+            let duration = views::to_time_spent_sum(nodes_of_target);
+            write!(out, "  ")?;
+            self.write_duration(out, duration, Color::Magenta)?;
+            writeln!(
+                out,
+                " - {kind:<6} {title}",
+                kind = format!("{}:", target.kind()),
+                title = self
+                    .style(Style::new().bold().fg(Color::Green))
+                    .paint(target.title())
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_final_summary(&self, out: &mut dyn Write, report: &Report) -> io::Result<()> {
+        // Print separator.
+        {
+            writeln!(out)?;
+            // same length as the week separator
+            writeln!(out, "{}", "-".repeat(59))?;
+            writeln!(out)?;
+        }
+
+        write!(
+            out,
+            "{total_time_key} ({days_amount:>2} days with records): ",
+            total_time_key = self.style(Style::new().bold()).paint("Total time"),
+            days_amount = report.days_with_records(),
+        )?;
+        self.write_duration(out, report.total_time(), Color::Blue)?;
+        writeln!(out)?;
+        let missing_days = report.missing_days();
+        if !missing_days.is_empty() {
+            let days = missing_days
+                .iter()
+                .map(|day| format!("{day}, {}", day.weekday()))
+                .collect::<Vec<_>>();
+            writeln!(
+                out,
+                "{}",
+                self.style(Style::new().bold().fg(Color::Yellow))
+                    .paint(format!(
+                        "Missing days ({}): {}",
+                        missing_days.len(),
+                        days.join("; ")
+                    ))
+            )?;
+        }
+        if let (Some(target), Some(balance)) =
+            (report.total_target(), report.balance_until(report.before))
+        {
+            self.write_target(out, "Target:", 35, report.total_time(), target)?;
+            self.write_balance(out, "Overtime balance:", 35, balance)?;
+        }
+
+        if report.extended_summary {
+            writeln!(out)?;
+            self.write_extended_summary(out, report)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the grand total, the total per section, and the matrix of
+    /// days and sections of a combined timesheet.
+    fn write_overview(
+        &self,
+        out: &mut dyn Write,
+        reports: &[Report],
+        titles: &[String],
+    ) -> io::Result<()> {
+        write!(
+            out,
+            "{}: ",
+            self.style(Style::new().bold()).paint("Grand total")
+        )?;
+        self.write_duration(out, super::grand_total(reports), Color::Blue)?;
+        writeln!(out)?;
+        writeln!(out)?;
+
+        let title_width = titles.iter().map(String::len).max().unwrap_or_default();
+        for (report, title) in reports.iter().zip(titles) {
+            write!(out, "  {title:<title_width$}  ")?;
+            self.write_duration(out, report.total_time(), Color::Blue)?;
+            writeln!(
+                out,
+                "  ({:>2} days with records)",
+                report.days_with_records()
+            )?;
+        }
+        writeln!(out)?;
+
+        // One row per day and one column per section, so that the matrix
+        // also fits for a whole month.
+        let widths = titles
+            .iter()
+            .map(|title| title.len().max(CELL_WIDTH))
+            .collect::<Vec<_>>();
+        write!(out, "{:<DAY_WIDTH$}", "")?;
+        for (title, width) in titles.iter().zip(&widths) {
+            write!(out, "  {title:>width$}")?;
+        }
+        writeln!(out, "  {:>CELL_WIDTH$}", "Total")?;
+
+        for day in super::span_days(reports) {
+            write!(out, "{:<DAY_WIDTH$}", format!("{day}, {}", day.weekday()))?;
+            for (report, width) in reports.iter().zip(&widths) {
+                let total = report.day_total(day);
+                write!(out, "  {}", " ".repeat(width - CELL_WIDTH))?;
+                if report.is_missing_day(day) {
+                    let missing = self.style(Style::new().bold().fg(Color::Yellow));
+                    write!(out, "{}", missing.paint("MISSING"))?;
+                } else if total.is_zero() {
+                    write!(out, "{:>CELL_WIDTH$}", "-")?;
+                } else {
+                    self.write_duration(out, total, Color::Magenta)?;
+                }
+            }
+            let total = reports.iter().map(|report| report.day_total(day)).sum();
+            write!(out, "  ")?;
+            self.write_duration(out, total, Color::Blue)?;
+            writeln!(out)?;
+        }
+
+        write!(out, "{:<DAY_WIDTH$}", "Total")?;
+        for (report, width) in reports.iter().zip(&widths) {
+            write!(out, "  {}", " ".repeat(width - CELL_WIDTH))?;
+            self.write_duration(out, report.total_time(), Color::Blue)?;
+        }
+        write!(out, "  ")?;
+        self.write_duration(out, super::grand_total(reports), Color::Blue)?;
+        writeln!(out)?;
+        Ok(())
+    }
+
+    fn write_all_weeks(&self, out: &mut dyn Write, report: &Report) -> io::Result<()> {
+        // Weeks without records are shown as well if they have missing days,
+        // so that a forgotten week doesn't go unnoticed.
+        let no_nodes = BTreeMap::new();
+        let weeks = report
+            .span_weeks()
+            .into_iter()
+            .map(|week| {
+                let nodes_by_day = report.weeks.get(&week).unwrap_or(&no_nodes);
+                (week, nodes_by_day, shown_days(report, week, nodes_by_day))
+            })
+            .filter(|(_, _, days)| !days.is_empty())
+            .collect::<Vec<_>>();
+        for (i, (week, nodes_by_day, days)) in weeks.iter().enumerate() {
+            self.write_week(out, report, *week, nodes_by_day, days)?;
+
+            let is_last = i == weeks.len() - 1;
+            if !is_last {
+                writeln!(out)?;
+            }
+        }
+
+        self.write_final_summary(out, report)
+    }
+}
+
+/// Returns the days of the week within the time span that are shown: days
+/// with records, days of absence, and missing days.
+fn shown_days<'n, 'a>(
+    report: &Report,
+    week: Week,
+    nodes_by_day: &'n BTreeMap<NaiveDate, Vec<&'a ResponseNode>>,
+) -> Vec<(NaiveDate, &'n [&'a ResponseNode])> {
+    report
+        .after
+        .iter_days()
+        .take_while(|day| *day <= report.before)
+        .filter(|day| week.contains(*day))
+        .filter_map(|day| {
+            let nodes = nodes_by_day.get(&day).map(Vec::as_slice);
+            let is_shown = report.absences.get(day).is_some() || report.is_missing_day(day);
+            nodes
+                .or_else(|| is_shown.then_some([].as_slice()))
+                .map(|nodes| (day, nodes))
+        })
+        .collect()
+}
+
+impl Renderer for TerminalRenderer {
+    fn render(&self, out: &mut dyn Write, report: &Report) -> anyhow::Result<()> {
+        if let Some(profile) = report.profile {
+            writeln!(out, "Profile  : {profile}")?;
+        }
+        writeln!(out, "Host     : {}", report.host)?;
+        let (scope_key, scope_value) = super::scope_header(report);
+        writeln!(out, "{scope_key:<9}: {scope_value}")?;
+        writeln!(out, "Time Span: {} - {}", report.after, report.before)?;
+
+        if report.nodes.is_empty() {
+            self.write_warning(
+                out,
+                "No entries found in this time span.\nUse --range, --after/--before, or --month to choose a different time span.",
+                0,
+            )?;
+        }
+        // Without records, the weeks are still shown to list missing days.
+        if !report.nodes.is_empty() || !report.missing_days().is_empty() {
+            self.write_all_weeks(out, report)?;
+        }
+        Ok(())
+    }
+
+    fn render_combined(&self, out: &mut dyn Write, reports: &[Report]) -> anyhow::Result<()> {
+        let titles = super::section_titles(reports);
+        self.write_overview(out, reports, &titles)?;
+
+        let delim_style = self.style(Style::new().bold());
+        for (report, title) in reports.iter().zip(&titles) {
+            writeln!(out)?;
+            writeln!(
+                out,
+                "{delim} {title} {delim}",
+                delim = delim_style.paint("######"),
+                title = delim_style.paint(title),
+            )?;
+            self.render(out, report)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::absences::Absences;
+    use crate::calendar::TimeZone;
+    use crate::gitlab_api::test_utils::{mr_node, node};
+    use crate::working_hours::WorkingHours;
+
+    #[test]
+    fn render_plain_snapshot() {
+        let mut weekend = node("2024-06-08T12:00:00Z", 600, "Issue B", None, None);
+        weekend.summary = Some("line 1\nline 2".to_string());
+        let nodes = [
+            node(
+                "2024-06-03T12:00:00Z",
+                5400,
+                "Issue A",
+                Some("Epic"),
+                Some("team/project"),
+            ),
+            weekend,
+            mr_node("2024-06-03T13:00:00Z", 1200, "Review"),
+        ];
+        let nodes = nodes.iter().collect::<Vec<_>>();
+        let mut report = Report::with_metadata(
+            &nodes,
+            "gitlab.example.com",
+            "user",
+            NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 6, 9).unwrap(),
+        );
+        report.extended_summary = true;
+
+        let mut out = Vec::new();
+        TerminalRenderer { colored: false }
+            .render(&mut out, &report)
+            .unwrap();
+
+        let expected = "\
+Host     : gitlab.example.com
+Username : user
+Time Span: 2024-06-03 - 2024-06-09
+====================== WEEK 2024-W23 ======================
+Total time:        2h 00m
+
+2024-06-03, Mon  ( 1h 50m)
+   1h 30m  Issue A
+           https://gitlab.example.com/-/issues/Issue-A
+           [Epic: Epic, Group: team/project]
+   0h 20m  MR: Review
+           https://gitlab.example.com/-/merge_requests/Review
+
+2024-06-04, Tue  ( 0h 00m)  [MISSING: No time logged!]
+
+2024-06-05, Wed  ( 0h 00m)  [MISSING: No time logged!]
+
+2024-06-06, Thu  ( 0h 00m)  [MISSING: No time logged!]
+
+2024-06-07, Fri  ( 0h 00m)  [MISSING: No time logged!]
+
+2024-06-08, Sat  ( 0h 10m)
+            ^ WARN: You shouldn't work on the weekend, right?
+   0h 10m  Issue B
+      ^ WARN: Less than 15 minutes! Is this correct?
+           https://gitlab.example.com/-/issues/Issue-B
+             line 1
+             line 2
+
+-----------------------------------------------------------
+
+Total time ( 2 days with records):  2h 00m
+Missing days (4): 2024-06-04, Tue; 2024-06-05, Wed; 2024-06-06, Thu; 2024-06-07, Fri
+
+   0h 30m - Epic:  <No Epic>
+   1h 30m - Epic:  Epic
+   1h 30m - Issue: Issue A
+   0h 10m - Issue: Issue B
+   0h 20m - MR:    Review
+";
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+
+    #[test]
+    fn render_shows_target_and_overtime_balance() {
+        let nodes = [
+            node("2024-06-03T12:00:00Z", 6 * 3600, "Issue A", None, None),
+            node("2024-06-04T12:00:00Z", 8 * 3600, "Issue B", None, None),
+            // Today, which only counts as far as time is logged.
+            node("2024-06-05T12:00:00Z", 3 * 3600, "Issue C", None, None),
+        ];
+        let nodes = nodes.iter().collect::<Vec<_>>();
+        let mut report = Report::with_metadata(
+            &nodes,
+            "gitlab.example.com",
+            "user",
+            NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 6, 9).unwrap(),
+        );
+        let config = toml::from_str("weekly = \"40h\"").unwrap();
+        report.working_hours = Some(WorkingHours::from_config(&config).unwrap());
+        report.today = NaiveDate::from_ymd_opt(2024, 6, 5).unwrap();
+
+        let mut out = Vec::new();
+        TerminalRenderer { colored: false }
+            .render(&mut out, &report)
+            .unwrap();
+        let text = String::from_utf8(out).unwrap();
+
+        assert!(
+            text.contains(
+                "\
+Total time:       17h 00m
+Target:           19h 00m  (-2h 00m)
+Balance:          -2h 00m
+"
+            ),
+            "{text}"
+        );
+        assert!(
+            text.contains(
+                "\
+Total time ( 3 days with records): 17h 00m
+Target:                            19h 00m  (-2h 00m)
+Overtime balance:                  -2h 00m
+"
+            ),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn render_marks_absences() {
+        let nodes = [node("2024-06-04T12:00:00Z", 3600, "Issue A", None, None)];
+        let nodes = nodes.iter().collect::<Vec<_>>();
+        let mut report = Report::with_metadata(
+            &nodes,
+            "gitlab.example.com",
+            "user",
+            NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 6, 4).unwrap(),
+        );
+        let config =
+            toml::from_str("holidays = [\"2024-06-04\"]\nsick = [\"2024-06-03\"]").unwrap();
+        report.absences = Absences::from_config(&config, TimeZone::Local).unwrap();
+        let config = toml::from_str("daily = \"8h\"").unwrap();
+        report.working_hours = Some(WorkingHours::from_config(&config).unwrap());
+
+        let mut out = Vec::new();
+        TerminalRenderer { colored: false }
+            .render(&mut out, &report)
+            .unwrap();
+        let text = String::from_utf8(out).unwrap();
+
+        assert!(
+            text.contains(
+                "\
+Total time:        1h 00m
+Target:            0h 00m  (+1h 00m)
+Balance:          +1h 00m
+
+2024-06-03, Mon  ( 0h 00m)  [Sick day]
+
+2024-06-04, Tue  ( 1h 00m)  [Holiday]
+            ^ WARN: You shouldn't work during an absence (Holiday), right?
+   1h 00m  Issue A
+"
+            ),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn render_shows_weeks_without_records() {
+        let nodes = [node(
+            "2024-06-03T12:00:00Z",
+            8 * 3600,
+            "Issue A",
+            None,
+            None,
+        )];
+        let nodes = nodes.iter().collect::<Vec<_>>();
+        let mut report = Report::with_metadata(
+            &nodes,
+            "gitlab.example.com",
+            "user",
+            NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 6, 11).unwrap(),
+        );
+        report.workdays = "mon".parse().unwrap();
+
+        let mut out = Vec::new();
+        TerminalRenderer { colored: false }
+            .render(&mut out, &report)
+            .unwrap();
+        let text = String::from_utf8(out).unwrap();
+
+        assert!(
+            text.contains(
+                "\
+====================== WEEK 2024-W24 ======================
+Total time:        0h 00m
+
+2024-06-10, Mon  ( 0h 00m)  [MISSING: No time logged!]
+"
+            ),
+            "{text}"
+        );
+        assert!(
+            text.contains("Missing days (1): 2024-06-10, Mon\n"),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn render_lists_missing_days_without_records() {
+        let mut report = Report::with_metadata(
+            &[],
+            "gitlab.example.com",
+            "user",
+            NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 6, 4).unwrap(),
+        );
+        report.today = NaiveDate::from_ymd_opt(2024, 6, 4).unwrap();
+
+        let mut out = Vec::new();
+        TerminalRenderer { colored: false }
+            .render(&mut out, &report)
+            .unwrap();
+        let text = String::from_utf8(out).unwrap();
+
+        assert!(
+            text.contains(
+                "No entries found in this time span.\nUse --range, --after/--before, or --month to choose a different time span."
+            ),
+            "{text}"
+        );
+        assert!(
+            text.contains(
+                "\
+====================== WEEK 2024-W23 ======================
+Total time:        0h 00m
+
+2024-06-03, Mon  ( 0h 00m)  [MISSING: No time logged!]
+
+-----------------------------------------------------------
+
+Total time ( 0 days with records):  0h 00m
+Missing days (1): 2024-06-03, Mon
+"
+            ),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn render_combined_shows_matrix_of_users_and_days() {
+        let alice = [node("2024-06-03T12:00:00Z", 5400, "Issue A", None, None)];
+        let alice = alice.iter().collect::<Vec<_>>();
+        let bob = [node("2024-06-04T12:00:00Z", 1800, "Issue B", None, None)];
+        let bob = bob.iter().collect::<Vec<_>>();
+        let after = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        let before = NaiveDate::from_ymd_opt(2024, 6, 5).unwrap();
+        let reports = [
+            Report::with_metadata(&alice, "gitlab.example.com", "alice", after, before),
+            Report::with_metadata(&bob, "gitlab.example.com", "bob", after, before),
+        ];
+
+        let mut out = Vec::new();
+        TerminalRenderer { colored: false }
+            .render_combined(&mut out, &reports)
+            .unwrap();
+        let text = String::from_utf8(out).unwrap();
+
+        let expected_overview = "\
+Grand total:  2h 00m
+
+  alice   1h 30m  ( 1 days with records)
+  bob     0h 30m  ( 1 days with records)
+
+                   alice      bob    Total
+2024-06-03, Mon   1h 30m  MISSING   1h 30m
+2024-06-04, Tue  MISSING   0h 30m   0h 30m
+2024-06-05, Wed  MISSING  MISSING   0h 00m
+Total             1h 30m   0h 30m   2h 00m
+";
+        assert!(text.starts_with(expected_overview), "{text}");
+        assert!(text.contains("###### alice ######\nHost     : gitlab.example.com"));
+        assert!(text.contains("###### bob ######"));
+    }
+}
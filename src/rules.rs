@@ -0,0 +1,485 @@
+/*
+MIT License
+
+Copyright (c) 2024 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Sanity-check rules for timelogs, such as warnings for negative durations
+//! or very long days.
+//!
+//! Every rule can be enabled or disabled, and its severity and threshold can
+//! be configured in the `rules` table of the config file.
+
+use crate::absences::Absence;
+use crate::calendar::Week;
+use crate::duration_to_hhmm;
+use crate::filter::parse_duration;
+use crate::gitlab_api::types::ResponseNode;
+use crate::views;
+use chrono::{Datelike, NaiveDate, Weekday};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+/// How severe a violation of a rule is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warn,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Info => "INFO",
+            Self::Warn => "WARN",
+            Self::Error => "ERROR",
+        })
+    }
+}
+
+/// The available rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleId {
+    /// A timelog has a negative duration.
+    NegativeDuration,
+    /// A timelog is shorter than the threshold.
+    ShortTimelog,
+    /// The time of a day exceeds the threshold.
+    LongDay,
+    /// Time is logged on a day that is not a workday, such as Saturday and
+    /// Sunday, or on a day of absence, such as a public holiday.
+    Weekend,
+    /// The time of a workday with records is below the threshold.
+    DailyMinimum,
+    /// The time of a week exceeds the threshold.
+    WeeklyMaximum,
+    /// A timelog has no summary.
+    MissingSummary,
+    /// A timelog is not associated with an epic.
+    MissingEpic,
+    /// No time is logged on a past workday. Only checked by the `check`
+    /// subcommand, as the timesheet highlights missing days anyway.
+    MissingDay,
+}
+
+impl RuleId {
+    const ALL: [Self; 9] = [
+        Self::NegativeDuration,
+        Self::ShortTimelog,
+        Self::LongDay,
+        Self::Weekend,
+        Self::DailyMinimum,
+        Self::WeeklyMaximum,
+        Self::MissingSummary,
+        Self::MissingEpic,
+        Self::MissingDay,
+    ];
+
+    /// Returns the name of the rule, as used in the config file.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::NegativeDuration => "negative_duration",
+            Self::ShortTimelog => "short_timelog",
+            Self::LongDay => "long_day",
+            Self::Weekend => "weekend",
+            Self::DailyMinimum => "daily_minimum",
+            Self::WeeklyMaximum => "weekly_maximum",
+            Self::MissingSummary => "missing_summary",
+            Self::MissingEpic => "missing_epic",
+            Self::MissingDay => "missing_day",
+        }
+    }
+
+    /// Returns the rule with its default settings.
+    const fn default_rule(self) -> Rule {
+        const fn hours(hours: u64) -> Option<Duration> {
+            Some(Duration::from_secs(hours * 60 * 60))
+        }
+        let (enabled, severity, threshold) = match self {
+            Self::NegativeDuration => (true, Severity::Error, None),
+            Self::ShortTimelog => (true, Severity::Warn, Some(Duration::from_secs(15 * 60))),
+            Self::LongDay => (true, Severity::Warn, hours(10)),
+            Self::Weekend => (true, Severity::Warn, None),
+            Self::DailyMinimum => (false, Severity::Warn, hours(8)),
+            Self::WeeklyMaximum => (false, Severity::Warn, hours(40)),
+            Self::MissingSummary | Self::MissingEpic => (false, Severity::Info, None),
+            Self::MissingDay => (true, Severity::Warn, None),
+        };
+        Rule {
+            id: self,
+            enabled,
+            severity,
+            threshold,
+        }
+    }
+}
+
+/// The settings of a rule in the config file. Unset values keep their
+/// defaults.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleConfig {
+    enabled: Option<bool>,
+    severity: Option<Severity>,
+    /// A duration such as `15m` or `10h`, for rules that have a threshold.
+    threshold: Option<String>,
+}
+
+impl RuleConfig {
+    /// Returns the values of `self`, with the missing ones taken from `base`.
+    fn or(self, base: &Self) -> Self {
+        Self {
+            enabled: self.enabled.or(base.enabled),
+            severity: self.severity.or(base.severity),
+            threshold: self.threshold.or_else(|| base.threshold.clone()),
+        }
+    }
+}
+
+/// The `rules` table of the config file, such as:
+///
+/// ```toml
+/// [rules.weekend]
+/// enabled = false
+///
+/// [rules.long_day]
+/// threshold = "12h"
+/// severity = "error"
+/// ```
+pub type RulesConfig = BTreeMap<RuleId, RuleConfig>;
+
+/// Returns the rules of `config`, with the missing rules and settings taken
+/// from `base`.
+pub fn rules_config_or(mut config: RulesConfig, base: &RulesConfig) -> RulesConfig {
+    for (id, base_rule) in base {
+        let rule = config.remove(id).unwrap_or_default().or(base_rule);
+        config.insert(*id, rule);
+    }
+    config
+}
+
+/// A rule with its effective settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule {
+    pub id: RuleId,
+    pub enabled: bool,
+    pub severity: Severity,
+    /// The threshold of the rule, if it has one.
+    pub threshold: Option<Duration>,
+}
+
+/// What a finding refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subject<'a> {
+    /// A timelog and the day it belongs to.
+    Timelog(NaiveDate, &'a ResponseNode),
+    Day(NaiveDate),
+    Week(Week),
+}
+
+/// A violation of a rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding<'a> {
+    pub rule: RuleId,
+    pub severity: Severity,
+    pub subject: Subject<'a>,
+    pub message: String,
+}
+
+/// All rules with their effective settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rules {
+    rules: [Rule; RuleId::ALL.len()],
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            rules: RuleId::ALL.map(RuleId::default_rule),
+        }
+    }
+}
+
+impl Rules {
+    /// Creates the rules from the defaults and the settings of the config
+    /// file.
+    pub fn from_config(config: &RulesConfig) -> Result<Self, String> {
+        let mut rules = Self::default();
+        for (id, rule_config) in config {
+            let rule = &mut rules.rules[*id as usize];
+            if let Some(enabled) = rule_config.enabled {
+                rule.enabled = enabled;
+            }
+            if let Some(severity) = rule_config.severity {
+                rule.severity = severity;
+            }
+            if let Some(threshold) = &rule_config.threshold {
+                if rule.threshold.is_none() {
+                    return Err(format!("rule `{}` has no threshold", id.name()));
+                }
+                rule.threshold = Some(
+                    parse_duration(threshold)
+                        .map_err(|e| format!("invalid threshold of rule `{}`: {e}", id.name()))?,
+                );
+            }
+        }
+        Ok(rules)
+    }
+
+    /// Returns the rule if it is enabled.
+    fn enabled(&self, id: RuleId) -> Option<&Rule> {
+        Some(&self.rules[id as usize]).filter(|rule| rule.enabled)
+    }
+
+    /// Returns the threshold of the rule if the rule is enabled.
+    fn threshold(&self, id: RuleId) -> Option<(&Rule, Duration)> {
+        self.enabled(id)
+            .and_then(|rule| rule.threshold.map(|threshold| (rule, threshold)))
+    }
+
+    /// Checks a single timelog of the given day.
+    pub fn check_timelog<'a>(&self, day: NaiveDate, node: &'a ResponseNode) -> Vec<Finding<'a>> {
+        let subject = Subject::Timelog(day, node);
+        let (duration_is_positive, duration) = node.timeSpent();
+        let mut findings = Vec::new();
+
+        if let Some(rule) = self.enabled(RuleId::NegativeDuration) {
+            if !duration_is_positive {
+                findings.push(rule.finding(
+                    subject,
+                    "You have logged this time as NEGATIVE: Update the ticket!".to_string(),
+                ));
+            }
+        }
+        if let Some((rule, min)) = self.threshold(RuleId::ShortTimelog) {
+            if duration < min {
+                findings.push(rule.finding(
+                    subject,
+                    format!("Less than {}! Is this correct?", describe(min)),
+                ));
+            }
+        }
+        if let Some(rule) = self.enabled(RuleId::MissingSummary) {
+            if node.summary.as_deref().is_none_or(|s| s.trim().is_empty()) {
+                findings.push(rule.finding(subject, "The timelog has no summary.".to_string()));
+            }
+        }
+        if let Some(rule) = self.enabled(RuleId::MissingEpic) {
+            if node.epic_name().is_none() {
+                findings.push(rule.finding(subject, "The timelog has no epic.".to_string()));
+            }
+        }
+        findings
+    }
+
+    /// Checks the timelogs of a day. `absence` is the absence on that day,
+    /// if any.
+    pub fn check_day(
+        &self,
+        day: NaiveDate,
+        nodes_of_day: &[&ResponseNode],
+        is_workday: bool,
+        absence: Option<&Absence>,
+    ) -> Vec<Finding<'static>> {
+        let subject = Subject::Day(day);
+        let total = views::to_time_spent_sum(nodes_of_day);
+        let is_weekend = matches!(day.weekday(), Weekday::Sat | Weekday::Sun);
+        let mut findings = Vec::new();
+
+        if let Some((rule, max)) = self.threshold(RuleId::LongDay) {
+            if total > max {
+                findings.push(rule.finding(
+                    subject,
+                    format!("More than {}! Is this correct?", describe(max)),
+                ));
+            }
+        }
+        if let Some((rule, min)) = self.threshold(RuleId::DailyMinimum) {
+            if is_workday && absence.is_none() && total < min {
+                findings.push(rule.finding(
+                    subject,
+                    format!("Less than {} on a working day!", describe(min)),
+                ));
+            }
+        }
+        if let Some(rule) = self.enabled(RuleId::Weekend) {
+            if let Some(absence) = absence {
+                findings.push(rule.finding(
+                    subject,
+                    format!("You shouldn't work during an absence ({absence}), right?"),
+                ));
+            } else if !is_workday {
+                let message = if is_weekend {
+                    "You shouldn't work on the weekend, right?"
+                } else {
+                    "You shouldn't work on a non-working day, right?"
+                };
+                findings.push(rule.finding(subject, message.to_string()));
+            }
+        }
+        findings
+    }
+
+    /// Checks a missing day, i.e., a past workday without any logged time.
+    pub fn check_missing_day(&self, day: NaiveDate) -> Vec<Finding<'static>> {
+        self.enabled(RuleId::MissingDay)
+            .map(|rule| rule.finding(Subject::Day(day), "No time logged!".to_string()))
+            .into_iter()
+            .collect()
+    }
+
+    /// Checks the timelogs of a week.
+    pub fn check_week(
+        &self,
+        week: Week,
+        nodes_by_day: &BTreeMap<NaiveDate, Vec<&ResponseNode>>,
+    ) -> Vec<Finding<'static>> {
+        let total = nodes_by_day
+            .values()
+            .map(|nodes| views::to_time_spent_sum(nodes))
+            .sum::<Duration>();
+        self.threshold(RuleId::WeeklyMaximum)
+            .filter(|(_, max)| total > *max)
+            .map(|(rule, max)| {
+                rule.finding(
+                    Subject::Week(week),
+                    format!("More than {} this week! Is this correct?", describe(max)),
+                )
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
+impl Subject<'_> {
+    /// Returns the date the subject refers to. For weeks, this is the
+    /// first day.
+    pub const fn date(&self) -> NaiveDate {
+        match self {
+            Self::Timelog(day, _) | Self::Day(day) => *day,
+            Self::Week(week) => week.first_day(),
+        }
+    }
+}
+
+impl Rule {
+    const fn finding(self, subject: Subject, message: String) -> Finding {
+        Finding {
+            rule: self.id,
+            severity: self.severity,
+            subject,
+            message,
+        }
+    }
+}
+
+/// Describes a threshold in words, such as `15 minutes` or `10 hours`.
+fn describe(duration: Duration) -> String {
+    match duration_to_hhmm(duration) {
+        (0, minutes) => format!("{minutes} minutes"),
+        (hours, 0) => format!("{hours} hours"),
+        (hours, minutes) => format!("{hours}h {minutes:02}m"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::WeekStart;
+    use crate::gitlab_api::test_utils::node;
+
+    fn rules(config: &str) -> Result<Rules, String> {
+        let config = toml::from_str::<RulesConfig>(config).map_err(|e| e.to_string())?;
+        Rules::from_config(&config)
+    }
+
+    fn rule_ids(findings: &[Finding]) -> Vec<RuleId> {
+        findings.iter().map(|finding| finding.rule).collect()
+    }
+
+    #[test]
+    fn default_rules_check_timelogs_and_days() {
+        let rules = Rules::default();
+        let short = node("2024-06-08T12:00:00Z", -600, "Issue", None, None);
+        let long = node("2024-06-08T13:00:00Z", 11 * 3600, "Issue", None, None);
+        let day = NaiveDate::from_ymd_opt(2024, 6, 8).unwrap();
+
+        let findings = rules.check_timelog(day, &short);
+        assert_eq!(
+            rule_ids(&findings),
+            [RuleId::NegativeDuration, RuleId::ShortTimelog]
+        );
+        assert_eq!(findings[0].severity, Severity::Error);
+        assert_eq!(
+            findings[1].message,
+            "Less than 15 minutes! Is this correct?"
+        );
+        assert!(rules.check_timelog(day, &long).is_empty());
+
+        let findings = rules.check_day(day, &[&long], false, None);
+        assert_eq!(rule_ids(&findings), [RuleId::LongDay, RuleId::Weekend]);
+        assert_eq!(findings[0].message, "More than 10 hours! Is this correct?");
+    }
+
+    #[test]
+    fn rules_are_configurable() {
+        let rules = rules(
+            r#"
+            weekend = { enabled = false }
+            long_day = { threshold = "12h", severity = "error" }
+            daily_minimum = { enabled = true, threshold = "7h30m" }
+            weekly_maximum = { enabled = true }
+            missing_epic = { enabled = true }
+            "#,
+        )
+        .unwrap();
+        let issue = node("2024-06-03T12:00:00Z", 11 * 3600, "Issue", None, None);
+        let saturday = NaiveDate::from_ymd_opt(2024, 6, 8).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+
+        assert!(rules.check_day(saturday, &[&issue], false, None).is_empty());
+        assert!(rules.check_day(monday, &[&issue], true, None).is_empty());
+        let findings = rules.check_day(monday, &[], true, None);
+        assert_eq!(findings[0].message, "Less than 7h 30m on a working day!");
+        assert_eq!(
+            rule_ids(&rules.check_timelog(monday, &issue)),
+            [RuleId::MissingEpic]
+        );
+
+        let week = BTreeMap::from([(monday, vec![&issue]), (saturday, vec![&issue; 3])]);
+        let findings = rules.check_week(Week::of(monday, WeekStart::Monday), &week);
+        assert_eq!(rule_ids(&findings), [RuleId::WeeklyMaximum]);
+    }
+
+    #[test]
+    fn invalid_rule_config_is_rejected() {
+        assert!(rules("unknown = { enabled = true }").is_err());
+        assert!(rules("weekend = { threshold = \"1h\" }").is_err());
+        assert!(rules("long_day = { threshold = \"long\" }").is_err());
+        assert!(rules("long_day = { severity = \"fatal\" }").is_err());
+    }
+}
@@ -24,8 +24,9 @@ SOFTWARE.
 
 //! Provides transform functions for different views into the data.
 
-use crate::gitlab_api::types::{Epic, Issue, ResponseNode};
-use chrono::{Datelike, IsoWeek, NaiveDate};
+use crate::calendar::{Calendar, TimeZone, Week};
+use crate::gitlab_api::types::{Epic, ResponseNode, Target};
+use chrono::NaiveDate;
 use std::collections::BTreeMap;
 use std::time::Duration;
 
@@ -48,30 +49,36 @@ fn group_notes_by_filter<'a, T: PartialEq + Ord>(
     map
 }
 
-/// Returns the nodes per [`IsoWeek`].
+/// Returns the nodes per [`Week`] of the calendar.
 pub fn to_nodes_by_week<'a>(
     nodes: &[&'a ResponseNode],
-) -> BTreeMap<IsoWeek, Vec<&'a ResponseNode>> {
-    group_notes_by_filter(nodes, |node| node.datetime().iso_week())
+    calendar: Calendar,
+) -> BTreeMap<Week, Vec<&'a ResponseNode>> {
+    group_notes_by_filter(nodes, |node| {
+        calendar.week_of(node.datetime(calendar.timezone))
+    })
 }
 
-/// Returns the nodes per [`NaiveDate`].
+/// Returns the nodes per [`NaiveDate`] in the given timezone.
 pub fn to_nodes_by_day<'a>(
     nodes: &[&'a ResponseNode],
+    timezone: TimeZone,
 ) -> BTreeMap<NaiveDate, Vec<&'a ResponseNode>> {
-    group_notes_by_filter(nodes, |node| node.datetime())
+    group_notes_by_filter(nodes, |node| node.datetime(timezone))
 }
 
 /// Returns the nodes per [`Epic`].
 pub fn to_nodes_by_epic<'a>(
     nodes: &[&'a ResponseNode],
 ) -> BTreeMap<Option<Epic>, Vec<&'a ResponseNode>> {
-    group_notes_by_filter(nodes, |node| node.issue.epic.clone())
+    group_notes_by_filter(nodes, |node| node.target.epic().cloned())
 }
 
-/// Returns the nodes per [`Issue`].
-pub fn to_nodes_by_issue<'a>(nodes: &[&'a ResponseNode]) -> BTreeMap<Issue, Vec<&'a ResponseNode>> {
-    group_notes_by_filter(nodes, |node| node.issue.clone())
+/// Returns the nodes per [`Target`], i.e., per issue and per merge request.
+pub fn to_nodes_by_target<'a>(
+    nodes: &[&'a ResponseNode],
+) -> BTreeMap<Target, Vec<&'a ResponseNode>> {
+    group_notes_by_filter(nodes, |node| node.target.clone())
 }
 
 /// Returns the time spent per day.
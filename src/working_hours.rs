@@ -0,0 +1,267 @@
+/*
+MIT License
+
+Copyright (c) 2024 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Workdays and working-hours targets, used to detect missing days and to
+//! compute the delta and the overtime balance of the logged time, e.g., for
+//! flextime accounts.
+
+use crate::filter::parse_duration;
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::Deserialize;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// The days of the week on which work is expected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Workdays {
+    /// Indexed by [`Weekday::num_days_from_monday`].
+    days: [bool; 7],
+}
+
+impl Default for Workdays {
+    /// Monday to Friday.
+    fn default() -> Self {
+        Self {
+            days: [true, true, true, true, true, false, false],
+        }
+    }
+}
+
+impl Workdays {
+    /// Returns whether work is expected on the given day.
+    pub fn contains(self, day: NaiveDate) -> bool {
+        self.days[day.weekday().num_days_from_monday() as usize]
+    }
+}
+
+impl FromStr for Workdays {
+    type Err = String;
+
+    /// Parses a comma-separated list of weekdays, such as `mon,tue,wed` or
+    /// `monday,tuesday`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut days = [false; 7];
+        for name in s.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            let weekday = name
+                .parse::<Weekday>()
+                .map_err(|_| format!("invalid weekday `{name}`"))?;
+            days[weekday.num_days_from_monday() as usize] = true;
+        }
+        Ok(Self { days })
+    }
+}
+
+/// The `working_hours` table of the config file, such as:
+///
+/// ```toml
+/// [working_hours]
+/// weekly = "40h"
+/// friday = "4h"
+/// ```
+///
+/// `daily` applies to each workday. The targets of single weekdays override
+/// it. With `weekly`, the targets of single weekdays are part of the week, and
+/// the remaining time is spread evenly over the other workdays, i.e., 9h from
+/// Monday to Thursday in the example above.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkingHoursConfig {
+    weekly: Option<String>,
+    daily: Option<String>,
+    monday: Option<String>,
+    tuesday: Option<String>,
+    wednesday: Option<String>,
+    thursday: Option<String>,
+    friday: Option<String>,
+    saturday: Option<String>,
+    sunday: Option<String>,
+}
+
+/// The target working time of a workday without an explicit target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DefaultTarget {
+    /// Spread evenly over the workdays without an explicit target, after
+    /// subtracting the explicit targets.
+    Weekly(Duration),
+    Daily(Duration),
+}
+
+/// The target working time per weekday.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkingHours {
+    default: DefaultTarget,
+    /// Explicit targets, indexed by [`Weekday::num_days_from_monday`].
+    per_weekday: [Option<Duration>; 7],
+}
+
+impl WorkingHoursConfig {
+    /// Returns the values of `self`, with the missing keys taken from `base`.
+    ///
+    /// `weekly` and `daily` are treated as a unit, as they are mutually
+    /// exclusive.
+    pub fn or(self, base: &Self) -> Self {
+        let has_default = self.weekly.is_some() || self.daily.is_some();
+        let (weekly, daily) = if has_default {
+            (self.weekly, self.daily)
+        } else {
+            (base.weekly.clone(), base.daily.clone())
+        };
+        Self {
+            weekly,
+            daily,
+            monday: self.monday.or_else(|| base.monday.clone()),
+            tuesday: self.tuesday.or_else(|| base.tuesday.clone()),
+            wednesday: self.wednesday.or_else(|| base.wednesday.clone()),
+            thursday: self.thursday.or_else(|| base.thursday.clone()),
+            friday: self.friday.or_else(|| base.friday.clone()),
+            saturday: self.saturday.or_else(|| base.saturday.clone()),
+            sunday: self.sunday.or_else(|| base.sunday.clone()),
+        }
+    }
+}
+
+impl WorkingHours {
+    /// Creates the working hours from the settings of the config file.
+    pub fn from_config(config: &WorkingHoursConfig) -> Result<Self, String> {
+        let parse = |key: &str, value: &Option<String>| {
+            value
+                .as_deref()
+                .map(parse_duration)
+                .transpose()
+                .map_err(|e| format!("invalid `working_hours.{key}`: {e}"))
+        };
+
+        let default = match (
+            parse("weekly", &config.weekly)?,
+            parse("daily", &config.daily)?,
+        ) {
+            (Some(_), Some(_)) => {
+                return Err(
+                    "`working_hours.weekly` and `working_hours.daily` are mutually exclusive"
+                        .to_string(),
+                );
+            }
+            (Some(weekly), None) => DefaultTarget::Weekly(weekly),
+            (None, Some(daily)) => DefaultTarget::Daily(daily),
+            (None, None) => DefaultTarget::Daily(Duration::ZERO),
+        };
+
+        let weekdays = [
+            ("monday", &config.monday),
+            ("tuesday", &config.tuesday),
+            ("wednesday", &config.wednesday),
+            ("thursday", &config.thursday),
+            ("friday", &config.friday),
+            ("saturday", &config.saturday),
+            ("sunday", &config.sunday),
+        ];
+        let mut per_weekday = [None; 7];
+        for (target, (key, value)) in per_weekday.iter_mut().zip(weekdays) {
+            *target = parse(key, value)?;
+        }
+        Ok(Self {
+            default,
+            per_weekday,
+        })
+    }
+
+    /// Returns the target working time of the given day.
+    pub fn target(&self, day: NaiveDate, workdays: Workdays) -> Duration {
+        if let Some(target) = self.per_weekday[day.weekday().num_days_from_monday() as usize] {
+            return target;
+        }
+        if !workdays.contains(day) {
+            return Duration::ZERO;
+        }
+        match self.default {
+            DefaultTarget::Weekly(weekly) => {
+                let explicit = self.per_weekday.iter().flatten().sum::<Duration>();
+                let other_workdays = workdays
+                    .days
+                    .iter()
+                    .zip(self.per_weekday)
+                    .filter(|(is_workday, target)| **is_workday && target.is_none())
+                    .count();
+                weekly.saturating_sub(explicit) / u32::try_from(other_workdays).unwrap_or(1).max(1)
+            }
+            DefaultTarget::Daily(daily) => daily,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn working_hours(config: &str) -> Result<WorkingHours, String> {
+        let config = toml::from_str::<WorkingHoursConfig>(config).map_err(|e| e.to_string())?;
+        WorkingHours::from_config(&config)
+    }
+
+    #[test]
+    fn working_hours_from_config() {
+        let hours = |hours: u64| Duration::from_secs(hours * 60 * 60);
+        let monday = NaiveDate::from_ymd_opt(2024, 6, 3).unwrap();
+        let friday = NaiveDate::from_ymd_opt(2024, 6, 7).unwrap();
+        let saturday = NaiveDate::from_ymd_opt(2024, 6, 8).unwrap();
+
+        let workdays = Workdays::default();
+        let weekly = working_hours("weekly = \"40h\"\nfriday = \"4h\"").unwrap();
+        assert_eq!(weekly.target(monday, workdays), hours(9));
+        assert_eq!(weekly.target(friday, workdays), hours(4));
+        assert_eq!(weekly.target(saturday, workdays), Duration::ZERO);
+        let week = monday.iter_days().take(7);
+        assert_eq!(
+            week.map(|day| weekly.target(day, workdays))
+                .sum::<Duration>(),
+            hours(40)
+        );
+
+        let workdays = "mon,tue,wed,thu".parse().unwrap();
+        assert_eq!(weekly.target(monday, workdays), hours(9));
+        let weekly = working_hours("weekly = \"40h\"").unwrap();
+        assert_eq!(weekly.target(monday, workdays), hours(10));
+
+        let daily = working_hours("daily = \"6h\"\nsaturday = \"2h\"").unwrap();
+        assert_eq!(daily.target(friday, workdays), Duration::ZERO);
+        assert_eq!(daily.target(saturday, workdays), hours(2));
+
+        assert!(working_hours("weekly = \"40h\"\ndaily = \"8h\"").is_err());
+        assert!(working_hours("monday = \"8 hours\"").is_err());
+        assert!(working_hours("mon = \"8h\"").is_err());
+    }
+
+    #[test]
+    fn workdays_from_str() {
+        let workdays = "Mon, tuesday,wed".parse::<Workdays>().unwrap();
+        assert_eq!(
+            workdays.days,
+            [true, true, true, false, false, false, false]
+        );
+        assert!(workdays.contains(NaiveDate::from_ymd_opt(2024, 6, 4).unwrap()));
+        assert!(!workdays.contains(NaiveDate::from_ymd_opt(2024, 6, 6).unwrap()));
+        assert!(!Workdays::default().contains(NaiveDate::from_ymd_opt(2024, 6, 8).unwrap()));
+        assert!("mon,someday".parse::<Workdays>().is_err());
+    }
+}
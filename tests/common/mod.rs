@@ -0,0 +1,274 @@
+/*
+MIT License
+
+Copyright (c) 2024 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Test harness with a local stand-in for the GitLab GraphQL API.
+//!
+//! [`FakeGitLab`] serves canned HTTP responses in order and records all
+//! requests. [`run_cli`] runs the CLI against it with an isolated home
+//! directory, so that neither the user's config file nor cache is used.
+
+#![allow(dead_code)]
+
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+/// A canned HTTP response of the fake server.
+#[derive(Debug, Clone)]
+pub struct CannedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl CannedResponse {
+    /// A `200 OK` response with the given JSON body.
+    pub fn json(body: &Value) -> Self {
+        Self {
+            status: 200,
+            headers: Vec::new(),
+            body: body.to_string(),
+        }
+    }
+
+    /// An empty response with the given HTTP status.
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: String::new(),
+        }
+    }
+
+    /// Adds the given header to the response.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// A request received by the fake server.
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    /// Request line and headers.
+    pub head: String,
+    pub body: Value,
+}
+
+/// Local HTTP server that mimics the GitLab GraphQL endpoint.
+#[derive(Debug)]
+pub struct FakeGitLab {
+    url: String,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl FakeGitLab {
+    /// Starts the server. Each response is served for exactly one
+    /// connection; afterwards, the server shuts down.
+    pub fn start(responses: Vec<CannedResponse>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let handle = {
+            let requests = requests.clone();
+            std::thread::spawn(move || {
+                for response in responses {
+                    let Ok((stream, _)) = listener.accept() else {
+                        return;
+                    };
+                    let request = handle_connection(stream, &response);
+                    requests.lock().unwrap().push(request);
+                }
+            })
+        };
+
+        Self {
+            url,
+            requests,
+            handle: Some(handle),
+        }
+    }
+
+    /// Base URL of the server, such as `http://127.0.0.1:1234`.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns all requests received so far.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn handle_connection(stream: TcpStream, response: &CannedResponse) -> RecordedRequest {
+    let mut reader = BufReader::new(stream);
+
+    let mut head = String::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if line == "\r\n" || line.is_empty() {
+            break;
+        }
+        head.push_str(&line);
+    }
+
+    let content_length = head
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.eq_ignore_ascii_case("content-length"))
+        .map_or(0, |(_, value)| value.trim().parse::<usize>().unwrap());
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).unwrap();
+
+    let headers = response
+        .headers
+        .iter()
+        .map(|(name, value)| format!("{name}: {value}\r\n"))
+        .collect::<String>();
+    let mut stream = reader.into_inner();
+    write!(
+        stream,
+        "HTTP/1.1 {} Canned\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{headers}Connection: close\r\n\r\n{}",
+        response.status,
+        response.body.len(),
+        response.body
+    )
+    .unwrap();
+    stream.flush().unwrap();
+
+    RecordedRequest {
+        head,
+        body: serde_json::from_slice(&body).unwrap_or(Value::Null),
+    }
+}
+
+/// Returns a fresh directory that serves as home directory for one test.
+pub fn test_home(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "gitlab-timelogs-test-{}-{name}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Runs the CLI against the given server for the week 2024-W23 with the
+/// additional arguments.
+pub fn run_cli(url: &str, home: &Path, args: &[&str]) -> Output {
+    run_cli_as(url, home, Some("user"), args)
+}
+
+/// Like [`run_cli`], but with the given `--username`, or none at all.
+pub fn run_cli_as(url: &str, home: &Path, username: Option<&str>, args: &[&str]) -> Output {
+    run_cli_between(url, home, username, ("2024-06-03", "2024-06-09"), args)
+}
+
+/// Like [`run_cli_as`], but for the given inclusive time span.
+pub fn run_cli_between(
+    url: &str,
+    home: &Path,
+    username: Option<&str>,
+    (after, before): (&str, &str),
+    args: &[&str],
+) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_gitlab-timelogs"))
+        .args(username.map(|username| format!("--username={username}")))
+        .args(["--host=gitlab.example.com", "--token=secret-token"])
+        .arg(format!("--after={after}"))
+        .arg(format!("--before={before}"))
+        .arg(format!("--url={url}"))
+        .args(args)
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home)
+        .env("XDG_CACHE_HOME", home.join("cache"))
+        .env("LOCALAPPDATA", home)
+        .env("NO_PROXY", "*")
+        .env_remove("HTTP_PROXY")
+        .env_remove("HTTPS_PROXY")
+        .env_remove("ALL_PROXY")
+        .env_remove("http_proxy")
+        .env_remove("https_proxy")
+        .env_remove("all_proxy")
+        .output()
+        .unwrap()
+}
+
+/// Returns a timelog node as GitLab returns it.
+pub fn timelog(spent_at: &str, time_spent_secs: i64, issue_title: &str) -> Value {
+    json!({
+        "spentAt": spent_at,
+        "timeSpent": time_spent_secs,
+        "summary": null,
+        "issue": {
+            "title": issue_title,
+            "webUrl": format!("https://gitlab.example.com/-/issues/{issue_title}"),
+            "epic": null,
+        },
+        "mergeRequest": null,
+        "project": { "group": null },
+    })
+}
+
+/// Returns the response of the `currentUser` GraphQL query.
+pub fn current_user(username: &str, name: &str) -> Value {
+    json!({
+        "data": {
+            "currentUser": { "username": username, "name": name },
+        },
+    })
+}
+
+/// Returns a page of the `timelogs` GraphQL query.
+pub fn timelogs_page(nodes: Vec<Value>, start_cursor: Option<&str>) -> Value {
+    json!({
+        "data": {
+            "timelogs": {
+                "nodes": nodes,
+                "pageInfo": {
+                    "hasPreviousPage": start_cursor.is_some(),
+                    "startCursor": start_cursor,
+                },
+            },
+        },
+    })
+}
+
+/// Returns a page of the `groupTimelogs` or `projectTimelogs` GraphQL query,
+/// depending on `kind`, which is either `group` or `project`.
+pub fn namespace_timelogs_page(kind: &str, nodes: Vec<Value>) -> Value {
+    let page = timelogs_page(nodes, None);
+    json!({
+        "data": {
+            kind: page["data"],
+        },
+    })
+}
@@ -0,0 +1,570 @@
+/*
+MIT License
+
+Copyright (c) 2024 Philipp Schuster
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! End-to-end tests of the CLI against a local fake GitLab server.
+
+mod common;
+
+use common::{
+    CannedResponse, FakeGitLab, current_user, namespace_timelogs_page, run_cli, run_cli_as,
+    run_cli_between, test_home, timelog, timelogs_page,
+};
+use serde_json::{Value, json};
+
+#[test]
+fn fetches_all_pages() {
+    let server = FakeGitLab::start(vec![
+        CannedResponse::json(&current_user("user", "Test User")),
+        CannedResponse::json(&timelogs_page(
+            vec![timelog("2024-06-04T12:00:00Z", 3600, "Newer")],
+            Some("cursor-1"),
+        )),
+        CannedResponse::json(&timelogs_page(
+            vec![timelog("2024-06-03T12:00:00Z", 1800, "Older")],
+            None,
+        )),
+    ]);
+    let home = test_home("fetches_all_pages");
+
+    let output = run_cli(server.url(), &home, &["--output=json"]);
+    assert!(output.status.success(), "{output:?}");
+
+    let json = serde_json::from_slice::<Value>(&output.stdout).unwrap();
+    assert_eq!(json["total_secs"], 5400);
+    assert_eq!(json["days_with_records"], 2);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    assert!(
+        requests[0].body["query"]
+            .as_str()
+            .unwrap()
+            .contains("currentUser")
+    );
+    assert!(requests[1].head.starts_with("POST /api/graphql "));
+    assert!(
+        requests[1]
+            .head
+            .to_lowercase()
+            .contains("authorization: bearer secret-token")
+    );
+    assert_eq!(requests[1].body["variables"]["username"], "user");
+    assert_eq!(requests[1].body["variables"]["before"], Value::Null);
+    assert_eq!(requests[2].body["variables"]["before"], "cursor-1");
+}
+
+#[test]
+fn uses_path_prefix_of_url() {
+    let server = FakeGitLab::start(vec![
+        CannedResponse::json(&current_user("user", "Test User")),
+        CannedResponse::json(&timelogs_page(
+            vec![timelog("2024-06-03T12:00:00Z", 3600, "Issue")],
+            None,
+        )),
+    ]);
+    let home = test_home("uses_path_prefix_of_url");
+
+    let url = format!("{}/gitlab/", server.url());
+    let output = run_cli(&url, &home, &["--output=json"]);
+    assert!(output.status.success(), "{output:?}");
+
+    let requests = server.requests();
+    assert!(requests[1].head.starts_with("POST /gitlab/api/graphql "));
+}
+
+#[test]
+fn renders_text_output() {
+    let server = FakeGitLab::start(vec![
+        CannedResponse::json(&current_user("user", "Test User")),
+        CannedResponse::json(&timelogs_page(
+            vec![
+                timelog("2024-06-03T12:00:00Z", 5400, "Issue A"),
+                timelog("2024-06-08T12:00:00Z", 600, "Issue B"),
+            ],
+            None,
+        )),
+    ]);
+    let home = test_home("renders_text_output");
+    let file = home.join("out.txt");
+
+    let output = run_cli(
+        server.url(),
+        &home,
+        &["--output-file", file.to_str().unwrap()],
+    );
+    assert!(output.status.success(), "{output:?}");
+
+    let text = std::fs::read_to_string(file).unwrap();
+    assert!(text.contains("WEEK 2024-W23"));
+    assert!(text.contains("   1h 30m  Issue A"));
+    assert!(text.contains("^ WARN: You shouldn't work on the weekend, right?"));
+    assert!(text.contains("Total time ( 2 days with records):  1h 40m"));
+}
+
+#[test]
+fn reports_unauthorized() {
+    // With a configured username, failing to fetch the owner of the token
+    // is only a warning, and the timelogs fail the same way.
+    let server = FakeGitLab::start(vec![
+        CannedResponse::status(401),
+        CannedResponse::status(401),
+    ]);
+    let home = test_home("reports_unauthorized");
+
+    let output = run_cli(server.url(), &home, &[]);
+    assert!(!output.status.success());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("401 Unauthorized"), "{stderr}");
+    assert!(stderr.contains("configured token is valid"), "{stderr}");
+}
+
+#[test]
+fn reports_forbidden() {
+    let server = FakeGitLab::start(vec![
+        CannedResponse::status(403),
+        CannedResponse::status(403),
+    ]);
+    let home = test_home("reports_forbidden");
+
+    let output = run_cli(server.url(), &home, &[]);
+    assert!(!output.status.success());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("403 Forbidden"), "{stderr}");
+    assert!(stderr.contains("read_api"), "{stderr}");
+}
+
+#[test]
+fn reports_graphql_errors() {
+    let server = FakeGitLab::start(vec![
+        CannedResponse::json(&current_user("user", "Test User")),
+        CannedResponse::json(&json!({
+            "errors": [{
+                "message": "Field 'foo' doesn't exist on type 'Timelog'",
+                "locations": [{ "line": 3, "column": 5 }],
+            }],
+        })),
+    ]);
+    let home = test_home("reports_graphql_errors");
+
+    let output = run_cli(server.url(), &home, &[]);
+    assert!(!output.status.success());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Field 'foo' doesn't exist on type 'Timelog' (at line 3, column 5)"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn errors_in_later_pages_abort() {
+    let server = FakeGitLab::start(vec![
+        CannedResponse::json(&current_user("user", "Test User")),
+        CannedResponse::json(&timelogs_page(
+            vec![timelog("2024-06-04T12:00:00Z", 3600, "Newer")],
+            Some("cursor-1"),
+        )),
+        CannedResponse::status(500),
+    ]);
+    let home = test_home("errors_in_later_pages_abort");
+
+    let output = run_cli(server.url(), &home, &[]);
+    assert!(!output.status.success());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("500"), "{stderr}");
+}
+
+#[test]
+fn uses_owner_of_token_as_default_username() {
+    let server = FakeGitLab::start(vec![
+        CannedResponse::json(&current_user("jdoe", "Jane Doe")),
+        CannedResponse::json(&timelogs_page(
+            vec![timelog("2024-06-03T12:00:00Z", 3600, "Issue")],
+            None,
+        )),
+    ]);
+    let home = test_home("uses_owner_of_token_as_default_username");
+
+    let output = run_cli_as(server.url(), &home, None, &[]);
+    assert!(output.status.success(), "{output:?}");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Username : jdoe (Jane Doe)"), "{stdout}");
+    assert_eq!(server.requests()[1].body["variables"]["username"], "jdoe");
+}
+
+#[test]
+fn warns_if_username_differs_from_owner_of_token() {
+    let server = FakeGitLab::start(vec![
+        CannedResponse::json(&current_user("jdoe", "Jane Doe")),
+        CannedResponse::json(&timelogs_page(Vec::new(), None)),
+    ]);
+    let home = test_home("warns_if_username_differs_from_owner_of_token");
+
+    let output = run_cli(server.url(), &home, &[]);
+    assert!(output.status.success(), "{output:?}");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("configured username `user` differs from `jdoe`"),
+        "{stderr}"
+    );
+    assert_eq!(server.requests()[1].body["variables"]["username"], "user");
+}
+
+#[test]
+fn uses_configured_username_if_owner_of_token_is_unavailable() {
+    let server = FakeGitLab::start(vec![
+        CannedResponse::status(500),
+        CannedResponse::json(&timelogs_page(
+            vec![timelog("2024-06-03T12:00:00Z", 3600, "Issue")],
+            None,
+        )),
+    ]);
+    let home = test_home("uses_configured_username_if_owner_of_token_is_unavailable");
+
+    let output = run_cli(server.url(), &home, &["--retries=0"]);
+    assert!(output.status.success(), "{output:?}");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Failed to fetch the owner of the token"),
+        "{stderr}"
+    );
+    assert_eq!(server.requests()[1].body["variables"]["username"], "user");
+
+    // Without a configured username, the owner of the token is required.
+    let server = FakeGitLab::start(vec![CannedResponse::status(500)]);
+    let output = run_cli_as(server.url(), &home, None, &["--retries=0"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn fetches_timelogs_of_each_team_member() {
+    let server = FakeGitLab::start(vec![
+        CannedResponse::json(&timelogs_page(
+            vec![timelog("2024-06-03T12:00:00Z", 3600, "Alice's issue")],
+            None,
+        )),
+        CannedResponse::json(&timelogs_page(
+            vec![timelog("2024-06-04T12:00:00Z", 1800, "Bob's issue")],
+            None,
+        )),
+    ]);
+    let home = test_home("fetches_timelogs_of_each_team_member");
+
+    let output = run_cli_as(
+        server.url(),
+        &home,
+        None,
+        &["--username=alice", "--username=bob", "--output=json"],
+    );
+    assert!(output.status.success(), "{output:?}");
+
+    let json = serde_json::from_slice::<Value>(&output.stdout).unwrap();
+    assert_eq!(json["total_secs"], 5400);
+    assert_eq!(json["reports"][0]["username"], "alice");
+    assert_eq!(json["reports"][1]["username"], "bob");
+    assert_eq!(json["reports"][1]["total_secs"], 1800);
+
+    // No lookup of the owner of the token in team mode.
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].body["variables"]["username"], "alice");
+    assert_eq!(requests[1].body["variables"]["username"], "bob");
+}
+
+#[test]
+fn fetches_timelogs_of_project_by_anyone() {
+    let mut alice = timelog("2024-06-03T12:00:00Z", 3600, "Issue A");
+    alice["user"] = json!({ "username": "alice", "name": "Alice" });
+    let mut bob = timelog("2024-06-04T12:00:00Z", 1800, "Issue B");
+    bob["user"] = json!({ "username": "bob", "name": "Bob" });
+    let server = FakeGitLab::start(vec![CannedResponse::json(&namespace_timelogs_page(
+        "project",
+        vec![alice, bob],
+    ))]);
+    let home = test_home("fetches_timelogs_of_project_by_anyone");
+
+    let file = home.join("out.txt");
+
+    let output = run_cli(
+        server.url(),
+        &home,
+        &[
+            "--project=team/app",
+            "--output-file",
+            file.to_str().unwrap(),
+        ],
+    );
+    assert!(output.status.success(), "{output:?}");
+
+    let text = std::fs::read_to_string(file).unwrap();
+    assert!(text.contains("Project  : team/app"), "{text}");
+    assert!(text.contains("[User: alice (Alice)]"), "{text}");
+    assert!(text.contains("[User: bob (Bob)]"), "{text}");
+
+    // No lookup of the owner of the token for projects.
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].body["operationName"], "projectTimelogs");
+    assert_eq!(requests[0].body["variables"]["fullPath"], "team/app");
+}
+
+#[test]
+fn reports_inaccessible_groups() {
+    let server = FakeGitLab::start(vec![CannedResponse::json(&json!({
+        "data": { "group": null },
+    }))]);
+    let home = test_home("reports_inaccessible_groups");
+
+    let output = run_cli(server.url(), &home, &["--group=secret"]);
+    assert!(!output.status.success());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("not accessible"), "{stderr}");
+}
+
+#[test]
+fn check_fails_on_findings_of_the_given_severity() {
+    let server = FakeGitLab::start(vec![
+        CannedResponse::json(&current_user("user", "Test User")),
+        CannedResponse::json(&timelogs_page(
+            vec![timelog("2024-06-04T12:00:00Z", -3600, "Negative")],
+            None,
+        )),
+    ]);
+    let home = test_home("check_fails_on_findings_of_the_given_severity");
+    let file = home.join("findings.txt");
+
+    let output = run_cli(
+        server.url(),
+        &home,
+        &["check", "--output-file", file.to_str().unwrap()],
+    );
+    assert_eq!(output.status.code(), Some(2), "{output:?}");
+
+    let text = std::fs::read_to_string(file).unwrap();
+    assert!(
+        text.contains("2024-06-04, Tue  ERROR  negative_duration  Negative:"),
+        "{text}"
+    );
+    assert!(
+        text.contains("2024-06-03, Mon  WARN   missing_day"),
+        "{text}"
+    );
+    assert!(!text.contains("WEEK"), "{text}");
+}
+
+#[test]
+fn check_succeeds_below_the_given_severity() {
+    let server = FakeGitLab::start(vec![
+        CannedResponse::json(&current_user("user", "Test User")),
+        CannedResponse::json(&timelogs_page(
+            vec![timelog("2024-06-03T12:00:00Z", 3600, "Issue")],
+            None,
+        )),
+    ]);
+    let home = test_home("check_succeeds_below_the_given_severity");
+
+    let output = run_cli(server.url(), &home, &["check", "--fail-on", "error"]);
+    assert!(output.status.success(), "{output:?}");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Findings: 0 errors, 4 warnings, 0 infos"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn fetches_long_time_spans_in_concurrent_chunks() {
+    let server = FakeGitLab::start(vec![
+        CannedResponse::json(&current_user("user", "Test User")),
+        CannedResponse::json(&timelogs_page(vec![], None)),
+        CannedResponse::json(&timelogs_page(vec![], None)),
+        CannedResponse::json(&timelogs_page(vec![], None)),
+    ]);
+    let home = test_home("fetches_long_time_spans_in_concurrent_chunks");
+
+    let output = run_cli_between(
+        server.url(),
+        &home,
+        Some("user"),
+        ("2024-01-01", "2024-03-31"),
+        &["--output=json", "--jobs=2"],
+    );
+    assert!(output.status.success(), "{output:?}");
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 4);
+    let mut start_dates = requests[1..]
+        .iter()
+        .map(|request| request.body["variables"]["startDate"].as_str().unwrap())
+        .map(|start_date| &start_date[..10])
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    start_dates.sort();
+    assert_eq!(start_dates, ["2024-01-01", "2024-02-01", "2024-03-03"]);
+}
+
+#[test]
+fn retries_transient_errors() {
+    let server = FakeGitLab::start(vec![
+        CannedResponse::json(&current_user("user", "Test User")),
+        CannedResponse::status(503).with_header("Retry-After", "0"),
+        CannedResponse::status(429)
+            .with_header("RateLimit-Remaining", "0")
+            .with_header("RateLimit-Reset", "0"),
+        CannedResponse::json(&timelogs_page(
+            vec![timelog("2024-06-04T12:00:00Z", 3600, "Work")],
+            None,
+        )),
+    ]);
+    let home = test_home("retries_transient_errors");
+
+    let output = run_cli(server.url(), &home, &["--output=json"]);
+    assert!(output.status.success(), "{output:?}");
+
+    let json = serde_json::from_slice::<Value>(&output.stdout).unwrap();
+    assert_eq!(json["total_secs"], 3600);
+    assert_eq!(server.requests().len(), 4);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("503 Service Unavailable"), "{stderr}");
+    assert!(stderr.contains("Retrying in 0s (1/3)"), "{stderr}");
+}
+
+#[test]
+fn gives_up_after_the_configured_retries() {
+    let server = FakeGitLab::start(vec![
+        CannedResponse::json(&current_user("user", "Test User")),
+        CannedResponse::status(502).with_header("Retry-After", "0"),
+        CannedResponse::status(504).with_header("Retry-After", "0"),
+    ]);
+    let home = test_home("gives_up_after_the_configured_retries");
+
+    let output = run_cli(server.url(), &home, &["--retries=1"]);
+    assert!(!output.status.success());
+    assert_eq!(server.requests().len(), 3);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("failed after 2 attempt(s)"), "{stderr}");
+    assert!(
+        stderr.contains("attempt 1: 502 Bad Gateway, retried after 0s"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("attempt 2: 504 Gateway Timeout"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn reports_graphql_errors_of_null_timelogs() {
+    let server = FakeGitLab::start(vec![
+        CannedResponse::json(&current_user("user", "Test User")),
+        CannedResponse::json(&json!({
+            "data": { "timelogs": null },
+            "errors": [{ "message": "Timeout on Timelog.spentAt", "locations": [] }],
+        })),
+    ]);
+    let home = test_home("reports_graphql_errors_of_null_timelogs");
+
+    let output = run_cli(server.url(), &home, &[]);
+    assert!(!output.status.success());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Timeout on Timelog.spentAt"), "{stderr}");
+    assert!(!stderr.contains("not accessible"), "{stderr}");
+}
+
+#[test]
+fn does_not_cache_partial_responses() {
+    let mut partial = timelogs_page(vec![timelog("2024-06-04T12:00:00Z", 3600, "Work")], None);
+    partial["errors"] = json!([{ "message": "Epic not accessible", "locations": [] }]);
+    let complete = timelogs_page(vec![timelog("2024-06-04T12:00:00Z", 7200, "Work")], None);
+    // The cache is per instance, so all runs use the same server.
+    let server = FakeGitLab::start(vec![
+        CannedResponse::json(&current_user("user", "Test User")),
+        CannedResponse::json(&partial),
+        CannedResponse::json(&current_user("user", "Test User")),
+        CannedResponse::json(&complete),
+        CannedResponse::json(&current_user("user", "Test User")),
+    ]);
+    let home = test_home("does_not_cache_partial_responses");
+    let total_secs = |output: &std::process::Output| {
+        assert!(output.status.success(), "{output:?}");
+        serde_json::from_slice::<Value>(&output.stdout).unwrap()["total_secs"].clone()
+    };
+
+    let output = run_cli(server.url(), &home, &["--output=json"]);
+    assert_eq!(total_secs(&output), 3600);
+
+    // The days of the partial response are fetched again.
+    let output = run_cli(server.url(), &home, &["--output=json"]);
+    assert_eq!(total_secs(&output), 7200);
+    assert_eq!(server.requests().len(), 4);
+
+    // Complete responses are cached.
+    let output = run_cli(server.url(), &home, &["--output=json"]);
+    assert_eq!(total_secs(&output), 7200);
+    assert_eq!(server.requests().len(), 5);
+}
+
+#[test]
+fn caches_completed_chunks_when_a_later_chunk_fails() {
+    let first_chunk = timelogs_page(vec![timelog("2024-05-02T12:00:00Z", 3600, "Work")], None);
+    let second_chunk = timelogs_page(vec![timelog("2024-06-04T12:00:00Z", 7200, "Work")], None);
+    // The cache is per instance, so all runs use the same server.
+    let server = FakeGitLab::start(vec![
+        CannedResponse::json(&current_user("user", "Test User")),
+        CannedResponse::json(&first_chunk),
+        CannedResponse::status(500),
+        CannedResponse::json(&current_user("user", "Test User")),
+        CannedResponse::json(&second_chunk),
+    ]);
+    let home = test_home("caches_completed_chunks_when_a_later_chunk_fails");
+    let span = ("2024-05-01", "2024-06-09");
+    let args = ["--output=json", "--jobs=1", "--retries=0"];
+
+    let output = run_cli_between(server.url(), &home, Some("user"), span, &args);
+    assert!(!output.status.success());
+    assert_eq!(server.requests().len(), 3);
+
+    // Only the failed chunk is fetched again.
+    let output = run_cli_between(server.url(), &home, Some("user"), span, &args);
+    assert!(output.status.success(), "{output:?}");
+    let json = serde_json::from_slice::<Value>(&output.stdout).unwrap();
+    assert_eq!(json["total_secs"], 3600 + 7200);
+    let requests = server.requests();
+    assert_eq!(requests.len(), 5);
+    assert!(
+        requests[4].body.to_string().contains("2024-06-01"),
+        "{:?}",
+        requests[4]
+    );
+}